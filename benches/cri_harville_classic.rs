@@ -23,6 +23,17 @@ fn criterion_benchmark(c: &mut Criterion) {
                 classic::summary_no_alloc(&dilated_probs, &mut alloc);
             });
         });
+        c.bench_function(&format!("cri_harville_classic_enumerated_{n}x{k}"), |b| {
+            b.iter(|| {
+                classic::summary_enumerated_no_alloc(&dilated_probs, &mut alloc);
+            });
+        });
+        let mut minimal_change_alloc = classic::MinimalChangeAlloc::new(n, k);
+        c.bench_function(&format!("cri_harville_classic_minimal_change_{n}x{k}"), |b| {
+            b.iter(|| {
+                classic::summary_minimal_change_no_alloc(&dilated_probs, &mut minimal_change_alloc);
+            });
+        });
     }
     bench(c, 3, 3);
     bench(c, 4, 4);
@@ -0,0 +1,57 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use dice::comb::bitmap::Bitmap;
+use dice::comb::fenwick::{pick_permutation_fenwick, rank_permutation, reset};
+use dice::comb::{count_permutations, pick_permutation};
+
+fn criterion_benchmark(c: &mut Criterion) {
+    fn bench(c: &mut Criterion, n: usize, r: usize) {
+        let permutations = count_permutations(n, r);
+        let stride = (permutations / 1_000).max(1);
+        let mut ordinals = vec![0; r];
+
+        let mut bitmap = Bitmap::empty(n);
+        c.bench_function(&format!("cri_pick_permutation_linear_{n}p{r}"), |b| {
+            b.iter(|| {
+                let mut checksum = 0;
+                for permutation in (0..permutations).step_by(stride) {
+                    pick_permutation(n, permutation, &mut bitmap, &mut ordinals);
+                    checksum += ordinals[0];
+                }
+                checksum
+            });
+        });
+
+        let mut tree = vec![0; n];
+        reset(&mut tree);
+        c.bench_function(&format!("cri_pick_permutation_fenwick_{n}p{r}"), |b| {
+            b.iter(|| {
+                let mut checksum = 0;
+                for permutation in (0..permutations).step_by(stride) {
+                    pick_permutation_fenwick(n, permutation, &mut tree, &mut ordinals);
+                    checksum += ordinals[0];
+                }
+                checksum
+            });
+        });
+
+        c.bench_function(&format!("cri_rank_permutation_{n}p{r}"), |b| {
+            b.iter(|| {
+                let mut checksum = 0;
+                for permutation in (0..permutations).step_by(stride) {
+                    pick_permutation_fenwick(n, permutation, &mut tree, &mut ordinals);
+                    checksum += rank_permutation(n, &ordinals, &mut tree);
+                }
+                checksum
+            });
+        });
+    }
+    bench(c, 8, 4);
+    bench(c, 8, 8);
+    bench(c, 16, 4);
+    bench(c, 16, 8);
+    bench(c, 32, 4);
+    bench(c, 64, 4);
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -72,6 +72,22 @@ impl<'a, W: BorrowMut<B>, B> CaptureMut<'a, W, B> {
     }
 }
 
+impl<T> CaptureMut<'_, Vec<T>, [T]> {
+    /// Shortens the captured vector or slice to `len` elements, without reallocating. Has no
+    /// effect if `len` is not less than the current length.
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        match self {
+            CaptureMut::Owned(owned) => owned.truncate(len),
+            CaptureMut::Borrowed(borrowed) => {
+                let slice = std::mem::take(borrowed);
+                let len = len.min(slice.len());
+                *borrowed = &mut slice[..len];
+            }
+        }
+    }
+}
+
 impl<W: BorrowMut<B> + Default, B: ?Sized> Default for CaptureMut<'_, W, B> {
     #[inline]
     fn default() -> Self {
@@ -162,4 +178,18 @@ mod tests {
         capture[0] = 100;
         assert_eq!([100, 20, 30], *capture);
     }
+
+    #[test]
+    fn capture_mut_truncate() {
+        let mut capture: CaptureMut<Vec<u32>, [u32]> = CaptureMut::Owned(vec![10, 20, 30]);
+        capture.truncate(2);
+        assert_eq!([10, 20], *capture);
+
+        let mut owned = vec![10, 20, 30];
+        let mut capture: CaptureMut<Vec<u32>, [u32]> = CaptureMut::Borrowed(&mut owned);
+        capture.truncate(1);
+        assert_eq!([10], *capture);
+        capture.truncate(5);
+        assert_eq!([10], *capture);
+    }
 }
@@ -2,10 +2,22 @@
 
 pub mod bitmap;
 pub mod combiner;
+pub mod composer;
+pub mod constrained_permuter;
 pub mod enumerator;
+pub mod fenwick;
+pub mod gray_enumerator;
+pub mod multiset_combiner;
+pub mod par;
+pub mod partitioner;
 pub mod permuter;
+pub mod revolving_combiner;
+pub mod sample;
 pub mod split_combiner;
 pub mod sticky_permuter;
+pub mod trotter_permuter;
+
+use bitmap::Bitmap;
 
 #[inline]
 pub fn pick_state(cardinalities: &[usize], state_index: usize, ordinals: &mut [usize]) {
@@ -39,6 +51,15 @@ pub fn count_states(cardinalities: &[usize]) -> usize {
         .fold(1, |acc, &num| acc * num)
 }
 
+/// Overflow-checked equivalent of [`count_states`], returning `None` if the number of states
+/// cannot be represented in a `usize`.
+#[inline(always)]
+pub fn checked_count_states(cardinalities: &[usize]) -> Option<usize> {
+    cardinalities
+        .iter()
+        .try_fold(1usize, |acc, &num| acc.checked_mul(num))
+}
+
 #[inline(always)]
 pub fn is_unique_quadratic(elements: &[usize]) -> bool {
     for (index, element) in elements.iter().enumerate() {
@@ -52,13 +73,13 @@ pub fn is_unique_quadratic(elements: &[usize]) -> bool {
 }
 
 #[inline(always)]
-pub fn is_unique_linear(elements: &[usize], bitmap: &mut [bool]) -> bool {
-    bitmap.fill(false);
+pub fn is_unique_linear(elements: &[usize], bitmap: &mut Bitmap) -> bool {
+    bitmap.clear_all();
     for &element in elements {
-        if bitmap[element] {
+        if bitmap.get(element) {
             return false;
         }
-        bitmap[element] = true;
+        bitmap.set(element);
     }
     true
 }
@@ -68,45 +89,153 @@ pub fn count_permutations(n: usize, r: usize) -> usize {
     ((n - r + 1)..=n).product()
 }
 
+/// Overflow-checked equivalent of [`count_permutations`], also admitting `r > n`, for which there
+/// are no permutations.
+#[inline(always)]
+pub fn checked_count_permutations(n: usize, r: usize) -> Option<usize> {
+    if r > n {
+        return Some(0);
+    }
+    ((n - r + 1)..=n).try_fold(1usize, |acc, factor| acc.checked_mul(factor))
+}
+
+/// The number of <sup>n</sup>C<sub>r</sub> combinations, panicking if it cannot be represented
+/// in a `usize`.
+#[inline(always)]
+pub fn count_combinations(n: usize, r: usize) -> usize {
+    checked_count_combinations(n, r).expect("number of combinations overflows usize")
+}
+
+/// Overflow-checked equivalent of [`count_combinations`]. Each partial product is itself a
+/// binomial coefficient, so it suffices to widen the intermediate product before dividing;
+/// `None` is returned only if the result itself cannot be represented in a `usize`.
+#[inline(always)]
+pub fn checked_count_combinations(n: usize, r: usize) -> Option<usize> {
+    if r > n {
+        return Some(0);
+    }
+    let r = r.min(n - r);
+    (0..r).try_fold(1usize, |acc, i| usize::try_from(scale(acc, n - i, i + 1)).ok())
+}
+
+/// `count * numerator / denominator`, widening the intermediate product.
+#[inline(always)]
+fn scale(count: usize, numerator: usize, denominator: usize) -> u128 {
+    count as u128 * numerator as u128 / denominator as u128
+}
+
+/// Exact bounds on the number of items remaining in a generator, given the exact count (if it
+/// can be represented in a `usize`).
+#[inline(always)]
+pub(crate) fn exact_size_hint(remaining: Option<usize>) -> (usize, Option<usize>) {
+    match remaining {
+        Some(remaining) => (remaining, Some(remaining)),
+        None => (usize::MAX, None),
+    }
+}
+
+/// The number of <sup>n</sup>C<sub>r</sub> combinations with repetition.
+#[inline(always)]
+pub fn count_multisets(n: usize, r: usize) -> usize {
+    checked_count_multisets(n, r).expect("number of multisets overflows usize")
+}
+
+/// Overflow-checked equivalent of [`count_multisets`].
+#[inline(always)]
+pub fn checked_count_multisets(n: usize, r: usize) -> Option<usize> {
+    if r == 0 {
+        return Some(1);
+    }
+    checked_count_combinations((n + r).checked_sub(1)?, r)
+}
+
+/// The number of ways of splitting `total` into `caps.len()` ordered parts, where each part
+/// cannot exceed its cap.
+#[inline]
+pub fn count_compositions(total: usize, caps: &[usize]) -> usize {
+    count_compositions_no_alloc(total, caps, &mut vec![0; total + 1])
+}
+
+/// Equivalent of [`count_compositions`], using `counts` (of length `total + 1`) as scratch
+/// space.
+#[inline]
+pub fn count_compositions_no_alloc(total: usize, caps: &[usize], counts: &mut [usize]) -> usize {
+    checked_count_compositions_no_alloc(total, caps, counts).expect("number of compositions overflows usize")
+}
+
+/// Overflow-checked equivalent of [`count_compositions_no_alloc`]. Counts the compositions of
+/// every subtotal over successively more parts, maintaining a sliding-window sum over the
+/// admissible values of the newest part.
+#[inline]
+pub fn checked_count_compositions_no_alloc(total: usize, caps: &[usize], counts: &mut [usize]) -> Option<usize> {
+    debug_assert_eq!(total + 1, counts.len(), "length of counts must be one more than the total");
+    counts.fill(0);
+    counts[0] = 1;
+    for &cap in caps {
+        // counts[subtotal] = sum of previous counts[subtotal - cap..=subtotal], in place from the top;
+        // every window sum is itself a count, so only the additions can overflow
+        let mut window = counts[total.saturating_sub(cap)..=total]
+            .iter()
+            .try_fold(0usize, |sum, &count| sum.checked_add(count))?;
+        for subtotal in (0..=total).rev() {
+            let previous = counts[subtotal];
+            counts[subtotal] = window;
+            window -= previous;
+            if subtotal > cap {
+                window = window.checked_add(counts[subtotal - cap - 1])?;
+            }
+        }
+    }
+    Some(counts[total])
+}
+
+/// The number of partitions of a set of `n` elements (the Bell number).
+#[inline]
+pub fn count_set_partitions(n: usize) -> usize {
+    count_set_partitions_no_alloc(&mut vec![0; n])
+}
+
+/// Equivalent of [`count_set_partitions`], using the `row` buffer (of length `n`) to evaluate
+/// the Bell triangle.
+#[inline]
+pub fn count_set_partitions_no_alloc(row: &mut [usize]) -> usize {
+    checked_count_set_partitions_no_alloc(row).expect("number of set partitions overflows usize")
+}
+
+/// Overflow-checked equivalent of [`count_set_partitions_no_alloc`].
+#[inline]
+pub fn checked_count_set_partitions_no_alloc(row: &mut [usize]) -> Option<usize> {
+    if row.is_empty() {
+        return Some(1);
+    }
+    row[0] = 1;
+    for len in 1..row.len() {
+        let mut previous = row[0];
+        row[0] = row[len - 1];
+        for index in 1..=len {
+            let current = if index < len { row[index] } else { 0 };
+            row[index] = row[index - 1].checked_add(previous)?;
+            previous = current;
+        }
+    }
+    Some(row[row.len() - 1])
+}
+
 #[inline]
 pub fn pick_permutation(
     cardinality: usize,
     permutation_index: usize,
-    bitmap: &mut [bool],
+    bitmap: &mut Bitmap,
     ordinals: &mut [usize],
 ) {
-    bitmap.fill(false);
+    bitmap.clear_all();
     let mut residual = permutation_index;
-    // let mut lowest = ordinals.len();
-    for index in 0..ordinals.len() {
+    for (index, ordinal) in ordinals.iter_mut().enumerate() {
         let cardinality = cardinality - index;
         let (quotient, remainder) = (residual / cardinality, residual % cardinality);
         residual = quotient;
-        // if remainder < lowest {
-        if index == 0 {
-            ordinals[index] = remainder;
-            bitmap[remainder] = true;
-            // if remainder < lowest {
-            //     lowest = remainder;
-            // }
-        } else {
-            let mut free = 0;
-            for b in 0..bitmap.len() {
-                if bitmap[b] {
-                    continue;
-                }
-                if free == remainder {
-                    ordinals[index] = b;
-                    bitmap[b] = true;
-                    // if b < lowest {
-                    //     lowest = b;
-                    // }
-                    break;
-                } else {
-                    free += 1;
-                }
-            }
-        }
+        *ordinal = bitmap.select_free(remainder).unwrap();
+        bitmap.set(*ordinal);
     }
 }
 
@@ -114,45 +243,71 @@ pub fn pick_permutation(
 pub fn pick_permutation_reverse(
     cardinality: usize,
     permutation_index: usize,
-    bitmap: &mut [bool],
+    bitmap: &mut Bitmap,
     ordinals: &mut [usize],
 ) {
-    bitmap.fill(false);
+    bitmap.clear_all();
     let mut residual = permutation_index;
-    // let mut lowest = ordinals.len();
-    let lim = ordinals.len();
-    for index in 0..lim {
+    for (index, ordinal) in ordinals.iter_mut().rev().enumerate() {
         let cardinality = cardinality - index;
         let (quotient, remainder) = (residual / cardinality, residual % cardinality);
         residual = quotient;
-        // if remainder < lowest {
-        if index == 0 {
-            ordinals[lim - index - 1] = remainder;
-            bitmap[remainder] = true;
-            // if remainder < lowest {
-            //     lowest = remainder;
-            // }
-        } else {
-            let mut free = 0;
-            for b in 0..bitmap.len() {
-                if bitmap[b] {
-                    continue;
-                }
-                if free == remainder {
-                    ordinals[lim - index - 1] = b;
-                    bitmap[b] = true;
-                    // if b < lowest {
-                    //     lowest = b;
-                    // }
-                    break;
-                } else {
-                    free += 1;
-                }
-            }
+        *ordinal = bitmap.select_free(remainder).unwrap();
+        bitmap.set(*ordinal);
+    }
+}
+
+/// Populates `ordinals` with the combination at `combination_index` in the lexicographic order
+/// of <sup>n</sup>C<sub>r</sub>, where `r` is the length of `ordinals`. This is the order in
+/// which [`Combiner`](combiner::Combiner) traverses the combinations.
+///
+/// The index is mapped to the colexicographic rank of the complementary (mirrored) combination,
+/// which is then decoded greedily using the combinatorial number system.
+#[inline]
+pub fn pick_combination(n: usize, combination_index: usize, ordinals: &mut [usize]) {
+    let r = ordinals.len();
+    let combinations = count_combinations(n, r);
+    debug_assert!(
+        combination_index < combinations,
+        "combination index {combination_index} is out of bounds for {n}C{r}"
+    );
+    if r == 0 {
+        return;
+    }
+    let mut residual = combinations - 1 - combination_index;
+    // binomials of the descending candidates are derived incrementally from C(n, r), using
+    // C(m - 1, k) = C(m, k)(m - k)/m and, on moving to the next position, C(m - 1, k - 1) = C(m, k)k/m;
+    // each is bounded by C(n, r), so only the intermediate products need widening
+    let mut mirrored = n - 1;
+    let mut count = scale(combinations, n - r, n) as usize;
+    for (index, ordinal) in ordinals.iter_mut().enumerate() {
+        let k = r - index;
+        while count > residual {
+            count = scale(count, mirrored - k, mirrored) as usize;
+            mirrored -= 1;
+        }
+        residual -= count;
+        *ordinal = n - 1 - mirrored;
+        if k > 1 {
+            count = scale(count, k, mirrored) as usize;
+            mirrored -= 1;
         }
     }
 }
 
+/// The inverse of [`pick_combination`], returning the lexicographic index of the given
+/// combination. The `ordinals` must be strictly ascending.
+#[inline]
+pub fn rank_combination(n: usize, ordinals: &[usize]) -> usize {
+    let r = ordinals.len();
+    let colex_rank = ordinals
+        .iter()
+        .enumerate()
+        .map(|(index, &ordinal)| count_combinations(n - 1 - ordinal, r - index))
+        .sum::<usize>();
+    count_combinations(n, r) - 1 - colex_rank
+}
+
 #[cfg(test)]
 mod tests {
     use std::fmt::Debug;
@@ -160,6 +315,7 @@ mod tests {
     use crate::stream::itemiser::Itemiser;
     use crate::stream::retain::Retain;
     use super::*;
+    use crate::comb::combiner::Combiner;
 
     pub(crate) fn iterate_generator<G>(generator: G) -> Vec<<G::Item as Retain>::Retained> where G: Generator, G::Item: Retain, <G::Item as Retain>::Retained: Debug {
         let outputs = generator.into_itemiser().into_vec();
@@ -222,10 +378,10 @@ mod tests {
 
     #[test]
     fn test_is_unique_linear() {
-        let mut bitmap_0 = vec![false; 0];
-        let mut bitmap_1 = vec![false; 1];
-        let mut bitmap_2 = vec![false; 2];
-        let mut bitmap_3 = vec![false; 3];
+        let mut bitmap_0 = Bitmap::empty(0);
+        let mut bitmap_1 = Bitmap::empty(1);
+        let mut bitmap_2 = Bitmap::empty(2);
+        let mut bitmap_3 = Bitmap::empty(3);
 
         assert!(is_unique_linear(&[], &mut bitmap_0));
         assert!(is_unique_linear(&[0], &mut bitmap_1));
@@ -254,10 +410,73 @@ mod tests {
         assert_eq!(24, count_permutations(4, 4));
     }
 
+    #[test]
+    fn test_count_combinations() {
+        assert_eq!(1, count_combinations(0, 0));
+        assert_eq!(0, count_combinations(0, 1));
+        assert_eq!(1, count_combinations(1, 0));
+        assert_eq!(1, count_combinations(1, 1));
+        assert_eq!(0, count_combinations(1, 2));
+        assert_eq!(4, count_combinations(4, 1));
+        assert_eq!(6, count_combinations(4, 2));
+        assert_eq!(4, count_combinations(4, 3));
+        assert_eq!(1, count_combinations(4, 4));
+        assert_eq!(15, count_combinations(6, 4));
+        assert_eq!(184_756, count_combinations(20, 10));
+    }
+
+    #[test]
+    fn pick_combination_near_overflow() {
+        let (n, r) = (64, 32);
+        let combinations = count_combinations(n, r);
+        let mut ordinals = vec![0; r];
+        for index in [0, 1, combinations / 2, combinations - 1] {
+            pick_combination(n, index, &mut ordinals);
+            assert!(ordinals.is_sorted_by(|a, b| a < b));
+            assert_eq!(index, rank_combination(n, &ordinals));
+        }
+        pick_combination(n, 0, &mut ordinals);
+        assert_eq!((0..32).collect::<Vec<_>>(), ordinals);
+        pick_combination(n, combinations - 1, &mut ordinals);
+        assert_eq!((32..64).collect::<Vec<_>>(), ordinals);
+        assert_eq!(Some(combinations), Combiner::new_at(n, r, 0).remaining());
+    }
+
+    #[test]
+    fn test_checked_counts() {
+        assert_eq!(Some(24), checked_count_states(&[2, 3, 4]));
+        assert_eq!(Some(1), checked_count_states(&[]));
+        assert_eq!(None, checked_count_states(&[usize::MAX, 2]));
+
+        assert_eq!(Some(24), checked_count_permutations(4, 3));
+        assert_eq!(Some(2_432_902_008_176_640_000), checked_count_permutations(20, 20));
+        assert_eq!(None, checked_count_permutations(21, 21));
+        assert_eq!(None, checked_count_permutations(100, 20));
+        assert_eq!(Some(0), checked_count_permutations(3, 4));
+        assert_eq!(Some(0), checked_count_permutations(3, 5));
+        assert_eq!(Some(0), checked_count_permutations(0, usize::MAX));
+
+        for n in 0..=20 {
+            for r in 0..=n + 1 {
+                assert_eq!(Some(count_combinations(n, r)), checked_count_combinations(n, r));
+            }
+        }
+        assert_eq!(Some(1_832_624_140_942_590_534), checked_count_combinations(64, 32));
+        assert_eq!(Some(14_226_520_737_620_288_370), checked_count_combinations(67, 33));
+        assert_eq!(None, checked_count_combinations(68, 34));
+
+        assert_eq!(Some(count_multisets(5, 3)), checked_count_multisets(5, 3));
+        assert_eq!(None, checked_count_multisets(40, 30));
+        assert_eq!(Some(0), checked_count_compositions_no_alloc(7, &[2, 2, 2], &mut [0; 8]));
+        assert_eq!(None, checked_count_compositions_no_alloc(200, &[200; 20], &mut [0; 201]));
+        assert_eq!(Some(4_638_590_332_229_999_353), checked_count_set_partitions_no_alloc(&mut [0; 25]));
+        assert_eq!(None, checked_count_set_partitions_no_alloc(&mut [0; 26]));
+    }
+
     fn generate_permutations(n: usize, r: usize) -> Vec<Vec<usize>> {
         let mut outputs = vec![];
         let permutations = count_permutations(n, r);
-        let mut bitmap = vec![false; n];
+        let mut bitmap = Bitmap::empty(n);
         println!("ordinals:");
         for index in 0..permutations {
             let mut ordinals = vec![0; r];
@@ -271,7 +490,7 @@ mod tests {
     fn generate_permutations_reverse(n: usize, r: usize) -> Vec<Vec<usize>> {
         let mut outputs = vec![];
         let permutations = count_permutations(n, r);
-        let mut bitmap = vec![false; n];
+        let mut bitmap = Bitmap::empty(n);
         println!("ordinals:");
         for index in (0..permutations).into_iter().rev() {
             let mut ordinals = vec![0; r];
@@ -406,4 +625,18 @@ mod tests {
         ];
         assert_eq!(inner_array_to_vec(expected_outputs), outputs);
     }
+
+    #[test]
+    fn test_pick_combination() {
+        for (n, r) in [(0, 0), (3, 0), (4, 1), (4, 3), (6, 4), (7, 7), (9, 5), (10, 2)] {
+            let expected_outputs = iterate_generator(Combiner::new(n, r));
+            assert_eq!(count_combinations(n, r), expected_outputs.len());
+            for (index, expected) in expected_outputs.iter().enumerate() {
+                let mut ordinals = vec![0; r];
+                pick_combination(n, index, &mut ordinals);
+                assert_eq!(expected, &ordinals);
+                assert_eq!(index, rank_combination(n, &ordinals));
+            }
+        }
+    }
 }
@@ -1,49 +1,170 @@
+//! A packed bitset, storing 64 ordinals per word. Fields of up to 64 runners fit in a single
+//! word, whereby occupancy queries reduce to a handful of register operations.
+//!
+//! The bits beyond [`len`](Bitmap::len) are kept clear at all times.
+
+use std::cmp::Ordering;
 use std::fmt::{Debug, Display, Formatter};
-use std::ops::{Deref, Index, IndexMut};
+use std::ops::Index;
+
+const WORD_BITS: usize = u64::BITS as usize;
 
-#[derive(Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
-pub struct Bitmap(Vec<bool>);
+#[derive(Default, Clone, Eq, PartialEq, Hash)]
+pub struct Bitmap {
+    words: Vec<u64>,
+    len: usize,
+}
 
 impl Bitmap {
     #[inline]
     pub fn empty(len: usize) -> Self {
-        Self(vec![false; len])
+        Self {
+            words: vec![0; len.div_ceil(WORD_BITS)],
+            len,
+        }
     }
 
     #[inline]
     pub fn full(len: usize) -> Self {
-        Self(vec![true; len])
+        let mut bitmap = Self::empty(len);
+        bitmap.words.fill(u64::MAX);
+        bitmap.mask_tail();
+        bitmap
     }
-    
+
+    /// The number of occupied ordinals.
     #[inline]
     pub fn size(&self) -> usize {
-        self.0.iter().filter(|b| **b).count()
+        self.count_ones()
+    }
+
+    #[inline]
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
     }
 
     #[inline]
     pub fn is_empty(&self) -> bool {
-        self.0.iter().all(|&b| !b)
+        self.words.iter().all(|&word| word == 0)
     }
-    
+
     #[inline]
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.len
     }
-    
+
+    /// The underlying words, the first holding ordinals 0 to 63 from its least significant bit.
     #[inline]
-    pub fn ordinals(&self) -> Iter {
+    pub fn as_words(&self) -> &[u64] {
+        &self.words
+    }
+
+    #[inline(always)]
+    pub fn get(&self, ordinal: usize) -> bool {
+        debug_assert!(ordinal < self.len, "ordinal {ordinal} out of bounds for length {}", self.len);
+        self.words[ordinal / WORD_BITS] & (1 << (ordinal % WORD_BITS)) != 0
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, ordinal: usize) {
+        debug_assert!(ordinal < self.len, "ordinal {ordinal} out of bounds for length {}", self.len);
+        self.words[ordinal / WORD_BITS] |= 1 << (ordinal % WORD_BITS);
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self, ordinal: usize) {
+        debug_assert!(ordinal < self.len, "ordinal {ordinal} out of bounds for length {}", self.len);
+        self.words[ordinal / WORD_BITS] &= !(1 << (ordinal % WORD_BITS));
+    }
+
+    /// Clears every ordinal.
+    #[inline]
+    pub fn clear_all(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Changes the length of the bitmap, clearing every ordinal. The underlying storage is
+    /// retained, reallocating only if it lacks the capacity.
+    #[inline]
+    pub fn resize(&mut self, len: usize) {
+        self.words.clear();
+        self.words.resize(len.div_ceil(WORD_BITS), 0);
+        self.len = len;
+    }
+
+    /// Shortens the bitmap to `len` ordinals, retaining the occupancy of the first `len`. Has no
+    /// effect if `len` is not less than the current length.
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.words.truncate(len.div_ceil(WORD_BITS));
+            self.len = len;
+            self.mask_tail();
+        }
+    }
+
+    #[inline]
+    pub fn ordinals(&self) -> Iter<'_> {
         Iter::new(self)
     }
-    
+
+    /// The lowest occupied ordinal at or above `start`.
     #[inline]
     pub fn next_occupied(&self, start: usize) -> Option<usize> {
-        for i in start..self.0.len() {
-            if self.0[i] {
-                return Some(i);
+        self.next_matching(start, |word| word)
+    }
+
+    /// The lowest free ordinal at or above `start`.
+    #[inline]
+    pub fn next_free(&self, start: usize) -> Option<usize> {
+        self.next_matching(start, |word| !word)
+            .filter(|&ordinal| ordinal < self.len)
+    }
+
+    /// The `nth` (zero-based) free ordinal.
+    #[inline]
+    pub fn select_free(&self, mut nth: usize) -> Option<usize> {
+        for (word_index, &word) in self.words.iter().enumerate() {
+            let mut free = !word;
+            let count = free.count_ones() as usize;
+            if nth < count {
+                for _ in 0..nth {
+                    free &= free - 1;
+                }
+                let ordinal = word_index * WORD_BITS + free.trailing_zeros() as usize;
+                return (ordinal < self.len).then_some(ordinal);
             }
+            nth -= count;
         }
         None
     }
+
+    #[inline(always)]
+    fn next_matching(&self, start: usize, transform: impl Fn(u64) -> u64) -> Option<usize> {
+        let mut word_index = start / WORD_BITS;
+        if word_index >= self.words.len() {
+            return None;
+        }
+        let mut word = transform(self.words[word_index]) & (u64::MAX << (start % WORD_BITS));
+        loop {
+            if word != 0 {
+                return Some(word_index * WORD_BITS + word.trailing_zeros() as usize);
+            }
+            word_index += 1;
+            if word_index == self.words.len() {
+                return None;
+            }
+            word = transform(self.words[word_index]);
+        }
+    }
+
+    #[inline]
+    fn mask_tail(&mut self) {
+        let tail_bits = self.len % WORD_BITS;
+        if tail_bits != 0 && let Some(last) = self.words.last_mut() {
+            *last &= (1 << tail_bits) - 1;
+        }
+    }
 }
 
 impl<I: IntoIterator<Item = usize>> From<(I, usize)> for Bitmap {
@@ -51,7 +172,7 @@ impl<I: IntoIterator<Item = usize>> From<(I, usize)> for Bitmap {
     fn from(ordinals_and_len: (I, usize)) -> Self {
         let mut bitmap = Self::empty(ordinals_and_len.1);
         for ordinal in ordinals_and_len.0 {
-            bitmap[ordinal] = true;
+            bitmap.set(ordinal);
         }
         bitmap
     }
@@ -90,7 +211,11 @@ impl Iterator for Iter<'_> {
 impl From<Vec<bool>> for Bitmap {
     #[inline]
     fn from(backing: Vec<bool>) -> Self {
-        Self(backing)
+        let len = backing.len();
+        Self::from((
+            backing.into_iter().enumerate().filter(|(_, b)| *b).map(|(ordinal, _)| ordinal),
+            len,
+        ))
     }
 }
 
@@ -99,22 +224,35 @@ impl Index<usize> for Bitmap {
 
     #[inline]
     fn index(&self, index: usize) -> &Self::Output {
-        &self.0[index]
+        if self.get(index) { &true } else { &false }
     }
 }
 
-impl IndexMut<usize> for Bitmap {
-    #[inline]
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.0[index]
+/// Orders bitmaps lexicographically by their bits, from ordinal 0 onwards, a clear bit preceding
+/// a set one and a bitmap preceding its extensions, as would a `Vec<bool>`.
+impl Ord for Bitmap {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let common_len = self.len.min(other.len);
+        for (index, (&word, &other_word)) in self.words.iter().zip(&other.words).enumerate() {
+            let bits = common_len.saturating_sub(index * WORD_BITS).min(WORD_BITS);
+            if bits == 0 {
+                break;
+            }
+            let mask = if bits == WORD_BITS { u64::MAX } else { (1 << bits) - 1 };
+            let diff = (word ^ other_word) & mask;
+            if diff != 0 {
+                let lowest = diff & diff.wrapping_neg();
+                return if word & lowest != 0 { Ordering::Greater } else { Ordering::Less };
+            }
+        }
+        self.len.cmp(&other.len)
     }
 }
 
-impl Deref for Bitmap {
-    type Target = [bool];
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
+impl PartialOrd for Bitmap {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -138,22 +276,25 @@ mod tests {
     fn get_set() {
         let mut bitmap = Bitmap::empty(3);
         assert!(!bitmap[0]);
-        bitmap[0] = true;
+        bitmap.set(0);
         assert!(bitmap[0]);
+        assert!(bitmap.get(0));
+        bitmap.clear(0);
+        assert!(!bitmap.get(0));
     }
     
     #[test]
     fn is_empty() {
         let mut bitmap = Bitmap::empty(2);
         assert!(bitmap.is_empty());
-        bitmap[1] = true;
+        bitmap.set(1);
         assert!(!bitmap.is_empty());
         
         let mut bitmap = Bitmap::full(2);
         assert!(!bitmap.is_empty());
-        bitmap[0] = false;
+        bitmap.clear(0);
         assert!(!bitmap.is_empty());
-        bitmap[1] = false;
+        bitmap.clear(1);
         assert!(bitmap.is_empty());
     }
 
@@ -190,4 +331,57 @@ mod tests {
         assert_eq!(Some(3), it.next());
         assert_eq!(None, it.next());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn multi_word() {
+        let mut bitmap = Bitmap::from(([0, 63, 64, 129], 130));
+        assert_eq!(4, bitmap.count_ones());
+        assert_eq!(vec![0, 63, 64, 129], bitmap.ordinals().collect::<Vec<_>>());
+        assert_eq!(Some(63), bitmap.next_occupied(1));
+        assert_eq!(Some(129), bitmap.next_occupied(65));
+        assert_eq!(Some(1), bitmap.next_free(0));
+        assert_eq!(Some(65), bitmap.next_free(63));
+        assert_eq!(None, bitmap.next_free(129));
+        assert_eq!(Some(1), bitmap.select_free(0));
+        assert_eq!(Some(62), bitmap.select_free(61));
+        assert_eq!(Some(65), bitmap.select_free(62));
+        assert_eq!(Some(128), bitmap.select_free(125));
+        assert_eq!(None, bitmap.select_free(126));
+
+        bitmap.truncate(64);
+        assert_eq!(2, bitmap.count_ones());
+        assert_eq!(None, bitmap.next_free(63));
+        bitmap.resize(3);
+        assert_eq!(Bitmap::empty(3), bitmap);
+    }
+
+    #[test]
+    fn full() {
+        let bitmap = Bitmap::full(70);
+        assert_eq!(70, bitmap.count_ones());
+        assert_eq!(None, bitmap.next_free(0));
+        assert_eq!(None, bitmap.select_free(0));
+        assert_eq!(Some(69), bitmap.next_occupied(69));
+        assert_eq!(None, bitmap.next_occupied(70));
+    }
+
+    #[test]
+    fn lexicographic_order() {
+        let to_bools = |bitmap: &Bitmap| (0..bitmap.len()).map(|ordinal| bitmap.get(ordinal)).collect::<Vec<_>>();
+        let mut bitmaps = vec![];
+        for len in [0, 1, 3, 64, 65, 130] {
+            for ordinals in [&[][..], &[0], &[2], &[0, 2], &[63], &[1, 64], &[64, 129]] {
+                let mut bitmap = Bitmap::empty(len);
+                for &ordinal in ordinals.iter().filter(|&&ordinal| ordinal < len) {
+                    bitmap.set(ordinal);
+                }
+                bitmaps.push(bitmap);
+            }
+        }
+        for bitmap in &bitmaps {
+            for other in &bitmaps {
+                assert_eq!(to_bools(bitmap).cmp(&to_bools(other)), bitmap.cmp(other), "{bitmap:?} vs {other:?}");
+            }
+        }
+    }
+}
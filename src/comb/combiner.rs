@@ -5,12 +5,15 @@
 //! lowest ordinals earlier.
 
 use crate::capture::CaptureMut;
+use crate::comb::{checked_count_combinations, exact_size_hint, pick_combination};
 use crate::stream::generator::Generator;
 
 #[derive(Debug)]
 pub struct Combiner<'a> {
     n: usize,
     ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    position: usize,
+    count: Option<usize>,
 }
 
 impl<'a> Combiner<'a> {
@@ -29,10 +32,40 @@ impl<'a> Combiner<'a> {
         for ordinal in 0..ordinals.len() {
             ordinals[ordinal] = ordinal;
         }
+        let count = checked_count_combinations(n, ordinals.len());
         Self {
-            ordinals, n,
+            ordinals, n, position: 0, count,
         }
     }
+
+    /// Creates a combiner positioned at the combination with the given lexicographic `index`,
+    /// such that the traversal resumes from that combination.
+    #[inline]
+    pub fn new_at(n: usize, r: usize, index: usize) -> Self {
+        Self::new_at_no_alloc(n, index, Self::alloc(r))
+    }
+
+    #[inline]
+    pub fn new_at_no_alloc(n: usize, index: usize, mut ordinals: CaptureMut<'a, Vec<usize>, [usize]>) -> Self {
+        pick_combination(n, index, &mut ordinals);
+        let count = checked_count_combinations(n, ordinals.len());
+        Self {
+            ordinals, n, position: index, count,
+        }
+    }
+
+    /// The lexicographic index of the current combination.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of combinations yet to be read, counting the current one, or `None` if the
+    /// total number of combinations cannot be represented in a `usize`.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.count.map(|count| count - self.position)
+    }
 }
 
 impl Generator for Combiner<'_> {
@@ -66,15 +99,22 @@ impl Generator for Combiner<'_> {
         for i in caret + 1..self.ordinals.len() {
             self.ordinals[i] = self.ordinals[i - 1] + 1;
         }
+        self.position += 1;
 
         true
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::comb::combiner::Combiner;
     use crate::comb::tests::{inner_array_to_vec, iterate_generator};
+    use crate::stream::generator::Generator;
 
     #[test]
     fn combiner_0c0() {
@@ -196,4 +236,30 @@ mod tests {
         ];
         assert_eq!(inner_array_to_vec(expected_outputs), outputs);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn combiner_6c4_at() {
+        let outputs = iterate_generator(Combiner::new(6, 4));
+        for index in 0..outputs.len() {
+            assert_eq!(&outputs[index..], iterate_generator(Combiner::new_at(6, 4, index)));
+        }
+    }
+
+    #[test]
+    fn combiner_exact_size() {
+        let mut combiner = Combiner::new(6, 4);
+        for position in 0..15 {
+            assert_eq!(position, combiner.position());
+            assert_eq!(Some(15 - position), combiner.remaining());
+            assert_eq!((15 - position, Some(15 - position)), combiner.size_hint());
+            assert_eq!(position < 14, combiner.advance());
+        }
+
+        let combiner = Combiner::new_at(6, 4, 5);
+        assert_eq!((5, Some(10)), (combiner.position(), combiner.remaining()));
+
+        let combiner = Combiner::new(100, 50);
+        assert_eq!(None, combiner.remaining());
+        assert_eq!((usize::MAX, None), combiner.size_hint());
+    }
+}
@@ -0,0 +1,191 @@
+//! A streaming generator of integer compositions: the ways of splitting a `total` into ordered
+//! parts, each bounded by its own cap. For example, the ways of splitting the goals scored by a
+//! team among its players.
+//!
+//! Compositions are traversed in lexicographic order. For a total of 2 over 3 uncapped parts,
+//! the initial composition `[0, 0, 2]` is succeeded by `[0, 1, 1]`, `[0, 2, 0]`, `[1, 0, 1]`,
+//! `[1, 1, 0]` and `[2, 0, 0]`.
+
+use crate::capture::CaptureMut;
+use crate::comb::{checked_count_compositions_no_alloc, exact_size_hint};
+use crate::stream::generator::Generator;
+
+#[derive(Debug)]
+pub struct Alloc<'a> {
+    pub parts: CaptureMut<'a, Vec<usize>, [usize]>,
+
+    /// Scratch space for sizing the traversal, of length `total + 1`.
+    pub counts: CaptureMut<'a, Vec<usize>, [usize]>,
+}
+
+impl Alloc<'_> {
+    #[inline]
+    pub fn new(total: usize, parts: usize) -> Self {
+        Self {
+            parts: vec![0; parts].into(),
+            counts: vec![0; total + 1].into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Composer<'a> {
+    caps: &'a [usize],
+    parts: CaptureMut<'a, Vec<usize>, [usize]>,
+    position: usize,
+    count: Option<usize>,
+}
+
+impl<'a> Composer<'a> {
+    /// Splits the `total` among `caps.len()` parts. If the `total` exceeds the sum of the caps,
+    /// there are no compositions and the traversal is empty.
+    #[inline]
+    pub fn new(total: usize, caps: &'a [usize]) -> Self {
+        Self::new_no_alloc(total, caps, Alloc::new(total, caps.len()))
+    }
+
+    #[inline]
+    pub fn new_no_alloc(total: usize, caps: &'a [usize], alloc: Alloc<'a>) -> Self {
+        let Alloc { mut parts, mut counts } = alloc;
+        debug_assert_eq!(caps.len(), parts.len(), "length of caps must equal the number of parts");
+        let count = checked_count_compositions_no_alloc(total, caps, &mut counts);
+        fill_from_right(&mut parts, caps, total);
+        Self {
+            caps,
+            parts,
+            position: 0,
+            count,
+        }
+    }
+
+    /// The number of compositions advanced over so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of compositions yet to be read, counting the current one, or `None` if the
+    /// total number of compositions cannot be represented in a `usize`.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.count.map(|count| count - self.position)
+    }
+}
+
+/// Assigns the `remaining` total to the `parts`, filling the rightmost parts to their caps first.
+#[inline(always)]
+fn fill_from_right(parts: &mut [usize], caps: &[usize], mut remaining: usize) {
+    for (part, &cap) in parts.iter_mut().zip(caps).rev() {
+        *part = remaining.min(cap);
+        remaining -= *part;
+    }
+}
+
+impl Generator for Composer<'_> {
+    type Item = [usize];
+
+    #[inline]
+    fn read(&self) -> &Self::Item {
+        &self.parts
+    }
+
+    #[inline]
+    fn advance(&mut self) -> bool {
+        let mut suffix_total = 0;
+        let mut caret = self.parts.len();
+        loop {
+            if caret == 0 {
+                return false;
+            }
+            caret -= 1;
+            if suffix_total > 0 && self.parts[caret] < self.caps[caret] {
+                break;
+            }
+            suffix_total += self.parts[caret];
+        }
+        self.parts[caret] += 1;
+        fill_from_right(&mut self.parts[caret + 1..], &self.caps[caret + 1..], suffix_total - 1);
+        self.position += 1;
+        true
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comb::composer::Composer;
+    use crate::comb::count_compositions;
+    use crate::comb::tests::{inner_array_to_vec, iterate_generator};
+    use crate::stream::generator::Generator;
+
+    #[test]
+    fn compose_0_over_2() {
+        let outputs = iterate_generator(Composer::new(0, &[5, 5]));
+        let expected_outputs = vec![
+            [0, 0]
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn compose_2_over_3() {
+        let composer = Composer::new(2, &[2, 2, 2]);
+        assert_eq!((6, Some(6)), composer.size_hint());
+        let outputs = iterate_generator(composer);
+        let expected_outputs = vec![
+            [0, 0, 2],
+            [0, 1, 1],
+            [0, 2, 0],
+            [1, 0, 1],
+            [1, 1, 0],
+            [2, 0, 0],
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn compose_capped() {
+        let composer = Composer::new(3, &[1, 2, 1]);
+        assert_eq!((3, Some(3)), composer.size_hint());
+        let outputs = iterate_generator(composer);
+        let expected_outputs = vec![
+            [0, 2, 1],
+            [1, 1, 1],
+            [1, 2, 0],
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn count_matches_traversal() {
+        for (total, caps) in [(5, vec![5, 5, 5, 5]), (4, vec![0, 3, 2]), (7, vec![3, 1, 4, 2])] {
+            let outputs = iterate_generator(Composer::new(total, &caps));
+            assert_eq!(count_compositions(total, &caps), outputs.len());
+            for output in outputs {
+                assert_eq!(total, output.iter().sum::<usize>());
+                assert!(output.iter().zip(&caps).all(|(part, cap)| part <= cap));
+            }
+        }
+    }
+
+    #[test]
+    fn compose_infeasible() {
+        let composer = Composer::new(4, &[1, 2]);
+        assert_eq!(Some(0), composer.remaining());
+        assert_eq!((0, Some(0)), composer.size_hint());
+        let outputs = iterate_generator(composer);
+        assert!(outputs.is_empty());
+    }
+
+    #[test]
+    fn compose_overflow() {
+        let caps = [200; 20];
+        let composer = Composer::new(200, &caps);
+        assert_eq!(None, composer.remaining());
+        assert_eq!((usize::MAX, None), composer.size_hint());
+    }
+}
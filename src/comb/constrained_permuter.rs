@@ -0,0 +1,298 @@
+//! A streaming permuter in which each position admits its own set of ordinals. For example,
+//! the podiums of a trifecta with runner 2 or 5 in first place and any runner but 7 in
+//! second place.
+//!
+//! Permutations are traversed in lexicographic order, and only the valid (distinct and
+//! admissible) permutations are visited: each position is assigned the lowest admissible ordinal
+//! not taken by a preceding position, backtracking once a position runs out of candidates.
+//!
+//! The valid permutations are counted on construction, costing <code>O(n·r·2<sup>r</sup>)</code>
+//! time and a table of <code>2<sup>r</sup></code> counts. Beyond [`MAX_COUNTED_POSITIONS`]
+//! positions, they are not counted: [`remaining`](ConstrainedPermuter::remaining) returns `None`
+//! and [`size_hint`](Generator::size_hint) has no upper bound until the traversal is exhausted.
+
+use crate::capture::CaptureMut;
+use crate::comb::bitmap::Bitmap;
+use crate::stream::generator::Generator;
+
+/// The largest number of positions for which the valid permutations are counted.
+pub const MAX_COUNTED_POSITIONS: usize = 16;
+
+#[derive(Debug)]
+pub struct Alloc<'a> {
+    pub bitmap: CaptureMut<'a, Bitmap>,
+    pub ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    /// The table of [`count_constrained_permutations`], of length [`counts_len`](Self::counts_len).
+    pub counts: CaptureMut<'a, Vec<usize>, [usize]>,
+}
+
+impl Alloc<'_> {
+    #[inline]
+    pub fn new(n: usize, r: usize) -> Self {
+        Self {
+            bitmap: Bitmap::empty(n).into(),
+            ordinals: vec![0; r].into(),
+            counts: vec![0; Self::counts_len(r)].into(),
+        }
+    }
+
+    /// The length of the counts table for `r` positions: <code>2<sup>r</sup></code>, or zero
+    /// beyond [`MAX_COUNTED_POSITIONS`].
+    #[inline]
+    pub fn counts_len(r: usize) -> usize {
+        if r <= MAX_COUNTED_POSITIONS { 1 << r } else { 0 }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConstrainedPermuter<'a> {
+    allowed: &'a [Bitmap],
+    bitmap: CaptureMut<'a, Bitmap>,
+    ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    position: usize,
+    exhausted: bool,
+    count: Option<usize>,
+}
+
+impl<'a> ConstrainedPermuter<'a> {
+    /// Permutes over `n` ordinals, with one position for each of the `allowed` sets (each of
+    /// length `n`).
+    #[inline]
+    pub fn new(n: usize, allowed: &'a [Bitmap]) -> Self {
+        Self::new_no_alloc(allowed, Alloc::new(n, allowed.len()))
+    }
+
+    #[inline]
+    pub fn new_no_alloc(allowed: &'a [Bitmap], alloc: Alloc<'a>) -> Self {
+        let Alloc { mut bitmap, ordinals, mut counts } = alloc;
+        let r = allowed.len();
+        debug_assert_eq!(r, ordinals.len(), "length of ordinals must equal the number of allowed sets");
+        debug_assert_eq!(Alloc::counts_len(r), counts.len(), "length of counts must equal Alloc::counts_len(r)");
+        debug_assert!(
+            allowed.iter().all(|set| set.len() == bitmap.len()),
+            "length of each allowed set must equal the length of the bitmap"
+        );
+        let count = if r <= MAX_COUNTED_POSITIONS {
+            count_constrained_permutations(allowed, &mut counts)
+        } else {
+            None
+        };
+        bitmap.clear_all();
+        let mut permuter = Self {
+            allowed,
+            bitmap,
+            ordinals,
+            position: 0,
+            exhausted: false,
+            count,
+        };
+        permuter.exhausted = !permuter.seek(0, 0);
+        permuter
+    }
+
+    /// The number of permutations advanced over so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of permutations yet to be read, counting the current one, or `None` if there
+    /// are more than [`MAX_COUNTED_POSITIONS`] positions or the count cannot be represented in a
+    /// `usize`.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.count.map(|count| count - self.position)
+    }
+
+    /// Assigns the lowest valid ordinals to positions `caret` onwards, starting the search for
+    /// position `caret` from `start`. Returns `false` if the preceding positions admit no
+    /// further permutation.
+    #[inline(always)]
+    fn seek(&mut self, mut caret: usize, mut start: usize) -> bool {
+        let r = self.ordinals.len();
+        while caret < r {
+            match self.next_candidate(caret, start) {
+                Some(ordinal) => {
+                    self.ordinals[caret] = ordinal;
+                    self.bitmap.set(ordinal);
+                    caret += 1;
+                    start = 0;
+                }
+                None => {
+                    if caret == 0 {
+                        return false;
+                    }
+                    caret -= 1;
+                    let ordinal = self.ordinals[caret];
+                    self.bitmap.clear(ordinal);
+                    start = ordinal + 1;
+                }
+            }
+        }
+        true
+    }
+
+    /// The lowest ordinal from `start` that is admitted at position `caret` and is not taken.
+    #[inline(always)]
+    fn next_candidate(&self, caret: usize, start: usize) -> Option<usize> {
+        let allowed = &self.allowed[caret];
+        let mut candidate = allowed.next_occupied(start);
+        while let Some(ordinal) = candidate {
+            if !self.bitmap.get(ordinal) {
+                return Some(ordinal);
+            }
+            candidate = allowed.next_occupied(ordinal + 1);
+        }
+        None
+    }
+}
+
+/// Counts the valid permutations by dynamic programming over the subsets of filled positions,
+/// admitting one ordinal at a time. `counts` must be of length <code>2<sup>r</sup></code>.
+/// Returns `None` if the count cannot be represented in a `usize`.
+#[inline]
+pub fn count_constrained_permutations(allowed: &[Bitmap], counts: &mut [usize]) -> Option<usize> {
+    let r = allowed.len();
+    debug_assert_eq!(1 << r, counts.len(), "length of counts must be 2^r");
+    let n = allowed.first().map_or(0, Bitmap::len);
+    counts.fill(0);
+    counts[0] = 1;
+    for ordinal in 0..n {
+        // descending order of subsets ensures that each ordinal fills at most one position
+        for filled in (0..counts.len()).rev() {
+            let count = counts[filled];
+            if count == 0 {
+                continue;
+            }
+            for (position, set) in allowed.iter().enumerate() {
+                if filled & (1 << position) == 0 && set.get(ordinal) {
+                    let target = &mut counts[filled | (1 << position)];
+                    *target = target.checked_add(count)?;
+                }
+            }
+        }
+    }
+    Some(counts[counts.len() - 1])
+}
+
+impl Generator for ConstrainedPermuter<'_> {
+    type Item = [usize];
+
+    #[inline]
+    fn read(&self) -> &Self::Item {
+        &self.ordinals
+    }
+
+    #[inline]
+    fn advance(&mut self) -> bool {
+        if self.exhausted || self.ordinals.is_empty() {
+            self.exhausted = true;
+            return false;
+        }
+        let caret = self.ordinals.len() - 1;
+        let ordinal = self.ordinals[caret];
+        self.bitmap.clear(ordinal);
+        if !self.seek(caret, ordinal + 1) {
+            self.exhausted = true;
+            return false;
+        }
+        self.position += 1;
+        true
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.exhausted {
+            return (0, Some(0));
+        }
+        match self.remaining() {
+            Some(remaining) => (remaining, Some(remaining)),
+            None => (1, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comb::bitmap::Bitmap;
+    use crate::comb::constrained_permuter::{ConstrainedPermuter, MAX_COUNTED_POSITIONS};
+    use crate::comb::count_permutations;
+    use crate::comb::permuter::Permuter;
+    use crate::comb::tests::{inner_array_to_vec, iterate_generator};
+    use crate::stream::generator::Generator;
+
+    #[test]
+    fn unconstrained_matches_permuter() {
+        let allowed = vec![Bitmap::full(4); 3];
+        let permuter = ConstrainedPermuter::new(4, &allowed);
+        assert_eq!(Some(24), permuter.remaining());
+        assert_eq!((24, Some(24)), permuter.size_hint());
+        let mut outputs = iterate_generator(permuter);
+        outputs.sort();
+        let mut expected_outputs = iterate_generator(Permuter::new(4, 3));
+        expected_outputs.sort();
+        assert_eq!(expected_outputs, outputs);
+    }
+
+    #[test]
+    fn constrained() {
+        let allowed = [
+            Bitmap::from(([2, 5], 8)),
+            Bitmap::from(((0..8).filter(|&ordinal| ordinal != 7), 8)),
+            Bitmap::from(([2, 3], 8)),
+        ];
+        let permuter = ConstrainedPermuter::new(8, &allowed);
+        assert_eq!(Some(15), permuter.remaining());
+        assert_eq!((15, Some(15)), permuter.size_hint());
+        let outputs = iterate_generator(permuter);
+        assert_eq!(15, outputs.len());
+        assert!(outputs.is_sorted());
+        for output in outputs {
+            assert!(allowed.iter().zip(&output).all(|(set, &ordinal)| set.get(ordinal)));
+            assert!(output[0] != output[1] && output[1] != output[2] && output[0] != output[2]);
+        }
+    }
+
+    #[test]
+    fn backtracking() {
+        let allowed = [
+            Bitmap::from(([0, 1], 3)),
+            Bitmap::from(([0], 3)),
+            Bitmap::from(([1, 2], 3)),
+        ];
+        let outputs = iterate_generator(ConstrainedPermuter::new(3, &allowed));
+        let expected_outputs = vec![
+            [1, 0, 2],
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn no_permutations() {
+        let allowed = [Bitmap::from(([0], 2)), Bitmap::from(([0], 2))];
+        let permuter = ConstrainedPermuter::new(2, &allowed);
+        assert_eq!((0, Some(0)), permuter.size_hint());
+        assert!(iterate_generator(permuter).is_empty());
+    }
+
+    #[test]
+    fn counted_positions() {
+        let allowed = vec![Bitmap::full(MAX_COUNTED_POSITIONS); MAX_COUNTED_POSITIONS];
+        let permuter = ConstrainedPermuter::new(MAX_COUNTED_POSITIONS, &allowed);
+        let count = count_permutations(MAX_COUNTED_POSITIONS, MAX_COUNTED_POSITIONS);
+        assert_eq!(Some(count), permuter.remaining());
+        assert_eq!((count, Some(count)), permuter.size_hint());
+    }
+
+    #[test]
+    fn uncounted_positions() {
+        let r = MAX_COUNTED_POSITIONS + 1;
+        let allowed = vec![Bitmap::full(40); r];
+        let mut permuter = ConstrainedPermuter::new(40, &allowed);
+        assert_eq!((0..r).collect::<Vec<_>>(), permuter.read());
+        assert!(permuter.advance());
+        assert_eq!(1, permuter.position());
+        assert_eq!(None, permuter.remaining());
+        assert_eq!((1, None), permuter.size_hint());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::capture::CaptureMut;
-use crate::comb::{count_states, pick_state};
+use crate::comb::{checked_count_states, pick_state};
 use crate::stream::itemiser::Itemiser;
 
 pub struct Enumerator<'a> {
@@ -24,7 +24,7 @@ impl<'a> Enumerator<'a> {
     pub fn new_no_alloc(cardinalities: &'a [usize], ordinals: CaptureMut<'a, Vec<usize>, [usize]>) -> Self {
         debug_assert_eq!(cardinalities.len(), ordinals.len(), "length of cardinalities must equal the length of ordinals");
         
-        let states = count_states(cardinalities);
+        let states = checked_count_states(cardinalities).expect("number of states overflows usize");
         Self {
             cardinalities,
             ordinals,
@@ -32,6 +32,18 @@ impl<'a> Enumerator<'a> {
             states,
         }
     }
+
+    /// The number of states yielded so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
+    /// The number of states yet to be yielded.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.states - self.index
+    }
 }
 
 impl Itemiser for Enumerator<'_> {
@@ -50,7 +62,7 @@ impl Itemiser for Enumerator<'_> {
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let remaining = self.states - self.index;
+        let remaining = self.remaining();
         (remaining, Some(remaining))
     }
 }
@@ -126,4 +138,21 @@ mod tests {
         ];
         assert_eq!(inner_array_to_vec(expected_outputs), outputs);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn exact_size() {
+        let mut enumerator = Enumerator::new(&[2, 3]);
+        for position in 0..6 {
+            assert_eq!((position, 6 - position), (enumerator.position(), enumerator.remaining()));
+            assert!(enumerator.next().is_some());
+        }
+        assert_eq!((6, 0), (enumerator.position(), enumerator.remaining()));
+        assert!(enumerator.next().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "number of states overflows usize")]
+    fn overflow() {
+        Enumerator::new(&[usize::MAX, 2]);
+    }
+}
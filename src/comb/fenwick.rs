@@ -0,0 +1,168 @@
+//! Permutation unranking and ranking in <code>O(r log n)</code>, using a Fenwick (binary indexed)
+//! tree over the free ordinals.
+//!
+//! [`pick_permutation`](super::pick_permutation) and
+//! [`pick_permutation_reverse`](super::pick_permutation_reverse) locate each free ordinal by
+//! scanning a bitmap, costing <code>O(n)</code> per position (albeit a word at a time). Here, the
+//! tree counts the free ordinals in each power-of-two span, so that the `k`-th free ordinal is
+//! found by binary lifting in <code>O(log n)</code>.
+//!
+//! The tree must be [reset](reset) before first use. Each function leaves it reset on return,
+//! restoring the ordinals it occupied, so that a tree may be reset once and reused across any
+//! number of calls. A prefix of a reset tree is itself a reset tree, so the tree may also be
+//! truncated to suit a smaller cardinality.
+
+/// Resets the tree to a state in which every ordinal is free.
+#[inline]
+pub fn reset(tree: &mut [usize]) {
+    for (index, count) in tree.iter_mut().enumerate() {
+        let position = index + 1;
+        *count = position & position.wrapping_neg();
+    }
+}
+
+/// Equivalent of [`pick_permutation`](super::pick_permutation).
+#[inline]
+pub fn pick_permutation_fenwick(
+    cardinality: usize,
+    permutation_index: usize,
+    tree: &mut [usize],
+    ordinals: &mut [usize],
+) {
+    debug_assert_eq!(cardinality, tree.len(), "tree length must equal the cardinality");
+    let mut residual = permutation_index;
+    for (index, ordinal) in ordinals.iter_mut().enumerate() {
+        let cardinality = cardinality - index;
+        let (quotient, remainder) = (residual / cardinality, residual % cardinality);
+        residual = quotient;
+        *ordinal = occupy(tree, remainder);
+    }
+    release(tree, ordinals);
+}
+
+/// Equivalent of [`pick_permutation_reverse`](super::pick_permutation_reverse).
+#[inline]
+pub fn pick_permutation_reverse_fenwick(
+    cardinality: usize,
+    permutation_index: usize,
+    tree: &mut [usize],
+    ordinals: &mut [usize],
+) {
+    debug_assert_eq!(cardinality, tree.len(), "tree length must equal the cardinality");
+    let mut residual = permutation_index;
+    for (index, ordinal) in ordinals.iter_mut().rev().enumerate() {
+        let cardinality = cardinality - index;
+        let (quotient, remainder) = (residual / cardinality, residual % cardinality);
+        residual = quotient;
+        *ordinal = occupy(tree, remainder);
+    }
+    release(tree, ordinals);
+}
+
+/// The inverse of [`pick_permutation_fenwick`] (and of [`pick_permutation`](super::pick_permutation)),
+/// returning the index of the permutation comprising the given `ordinals`.
+#[inline]
+pub fn rank_permutation(cardinality: usize, ordinals: &[usize], tree: &mut [usize]) -> usize {
+    debug_assert_eq!(cardinality, tree.len(), "tree length must equal the cardinality");
+    let mut permutation_index = 0;
+    let mut radix = 1;
+    for (index, &ordinal) in ordinals.iter().enumerate() {
+        let remainder = count_free_below(tree, ordinal);
+        update(tree, ordinal, usize::wrapping_sub);
+        permutation_index += remainder * radix;
+        radix *= cardinality - index;
+    }
+    release(tree, ordinals);
+    permutation_index
+}
+
+/// Occupies the `rank`-th (zero-based) free ordinal, returning it.
+#[inline(always)]
+fn occupy(tree: &mut [usize], mut rank: usize) -> usize {
+    let len = tree.len();
+    debug_assert!(rank < count_free_below(tree, len), "rank {rank} exceeds the free ordinals");
+    let mut position = 0;
+    let mut step = if len == 0 { 0 } else { 1 << len.ilog2() };
+    while step > 0 {
+        let next = position + step;
+        if next <= len && tree[next - 1] <= rank {
+            position = next;
+            rank -= tree[next - 1];
+        }
+        step >>= 1;
+    }
+    update(tree, position, usize::wrapping_sub);
+    position
+}
+
+/// The number of free ordinals less than `ordinal`.
+#[inline(always)]
+fn count_free_below(tree: &[usize], ordinal: usize) -> usize {
+    let mut count = 0;
+    let mut position = ordinal;
+    while position > 0 {
+        count += tree[position - 1];
+        position &= position - 1;
+    }
+    count
+}
+
+#[inline(always)]
+fn update(tree: &mut [usize], ordinal: usize, op: fn(usize, usize) -> usize) {
+    let mut position = ordinal + 1;
+    while position <= tree.len() {
+        tree[position - 1] = op(tree[position - 1], 1);
+        position += position & position.wrapping_neg();
+    }
+}
+
+#[inline(always)]
+fn release(tree: &mut [usize], ordinals: &[usize]) {
+    for &ordinal in ordinals {
+        update(tree, ordinal, usize::wrapping_add);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comb::bitmap::Bitmap;
+    use crate::comb::{count_permutations, pick_permutation, pick_permutation_reverse};
+
+    #[test]
+    fn matches_linear_scan() {
+        for (n, r) in [(0, 0), (1, 1), (4, 0), (4, 2), (4, 4), (7, 3), (9, 9)] {
+            let mut tree = vec![0; n];
+            reset(&mut tree);
+            let mut bitmap = Bitmap::empty(n);
+            let (mut expected, mut ordinals) = (vec![0; r], vec![0; r]);
+            for index in 0..count_permutations(n, r) {
+                pick_permutation(n, index, &mut bitmap, &mut expected);
+                pick_permutation_fenwick(n, index, &mut tree, &mut ordinals);
+                assert_eq!(expected, ordinals);
+                assert_eq!(index, rank_permutation(n, &ordinals, &mut tree));
+
+                pick_permutation_reverse(n, index, &mut bitmap, &mut expected);
+                pick_permutation_reverse_fenwick(n, index, &mut tree, &mut ordinals);
+                assert_eq!(expected, ordinals);
+            }
+        }
+    }
+
+    #[test]
+    fn tree_restored() {
+        let mut tree = vec![0; 10];
+        reset(&mut tree);
+        let initial = tree.clone();
+        let mut ordinals = [0; 4];
+        pick_permutation_fenwick(10, 1234, &mut tree, &mut ordinals);
+        assert_eq!(initial, tree);
+        rank_permutation(10, &ordinals, &mut tree);
+        assert_eq!(initial, tree);
+
+        tree.truncate(6);
+        let mut truncated = vec![0; 6];
+        reset(&mut truncated);
+        assert_eq!(truncated, tree);
+    }
+}
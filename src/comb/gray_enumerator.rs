@@ -0,0 +1,184 @@
+//! A streaming enumerator of states in reflected mixed-radix Gray code order, such that each
+//! successive state differs from its predecessor in exactly one ordinal, by exactly one.
+//!
+//! As with [`Enumerator`](super::enumerator::Enumerator), the first ordinal varies fastest. For
+//! example, for cardinalities `[2, 3]`, the initial state `[0, 0]` is succeeded by `[1, 0]`,
+//! `[1, 1]`, `[0, 1]`, `[0, 2]` and `[1, 2]`.
+
+use crate::capture::CaptureMut;
+use crate::comb::bitmap::Bitmap;
+use crate::comb::{checked_count_states, exact_size_hint};
+use crate::stream::generator::Generator;
+
+#[derive(Debug)]
+pub struct Alloc<'a> {
+    pub ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    pub descending: CaptureMut<'a, Bitmap>,
+}
+
+impl Alloc<'_> {
+    #[inline]
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            ordinals: vec![0; dimensions].into(),
+            descending: Bitmap::empty(dimensions).into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GrayEnumerator<'a> {
+    cardinalities: &'a [usize],
+    ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    descending: CaptureMut<'a, Bitmap>,
+    changed: [usize; 1],
+    changes: usize,
+    position: usize,
+    count: Option<usize>,
+}
+
+impl<'a> GrayEnumerator<'a> {
+    #[inline]
+    pub fn new(cardinalities: &'a [usize]) -> Self {
+        Self::new_no_alloc(cardinalities, Alloc::new(cardinalities.len()))
+    }
+
+    #[inline]
+    pub fn new_no_alloc(cardinalities: &'a [usize], alloc: Alloc<'a>) -> Self {
+        let Alloc { mut ordinals, mut descending } = alloc;
+        debug_assert_eq!(cardinalities.len(), ordinals.len(), "length of cardinalities must equal the length of ordinals");
+        debug_assert_eq!(cardinalities.len(), descending.len(), "length of cardinalities must equal the length of the bitmap");
+        ordinals.fill(0);
+        descending.clear_all();
+        let count = checked_count_states(cardinalities);
+        Self {
+            cardinalities,
+            ordinals,
+            descending,
+            changed: [0],
+            changes: 0,
+            position: 0,
+            count,
+        }
+    }
+
+    /// The positions of the ordinals changed by the last advance; empty prior to the first.
+    #[inline]
+    pub fn changed(&self) -> &[usize] {
+        &self.changed[..self.changes]
+    }
+
+    /// The number of states advanced over so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of states yet to be read, counting the current one, or `None` if the total
+    /// number of states cannot be represented in a `usize`.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.count.map(|count| count - self.position)
+    }
+}
+
+impl Generator for GrayEnumerator<'_> {
+    type Item = [usize];
+
+    #[inline]
+    fn read(&self) -> &Self::Item {
+        &self.ordinals
+    }
+
+    #[inline]
+    fn advance(&mut self) -> bool {
+        // the lowest ordinal that can move in its direction moves; every ordinal below it has
+        // reached an end of its range, and reverses direction
+        for (index, &cardinality) in self.cardinalities.iter().enumerate() {
+            let ordinal = self.ordinals[index];
+            if self.descending.get(index) {
+                if ordinal > 0 {
+                    self.ordinals[index] = ordinal - 1;
+                    self.changed[0] = index;
+                    self.changes = 1;
+                    self.position += 1;
+                    return true;
+                }
+                self.descending.clear(index);
+            } else {
+                if ordinal + 1 < cardinality {
+                    self.ordinals[index] = ordinal + 1;
+                    self.changed[0] = index;
+                    self.changes = 1;
+                    self.position += 1;
+                    return true;
+                }
+                self.descending.set(index);
+            }
+        }
+        false
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comb::count_states;
+    use crate::comb::enumerator::Enumerator;
+    use crate::comb::tests::{inner_array_to_vec, iterate_generator};
+    use crate::stream::itemiser::Itemiser;
+
+    #[test]
+    fn gray_2x3() {
+        let outputs = iterate_generator(GrayEnumerator::new(&[2, 3]));
+        let expected_outputs = vec![
+            [0, 0],
+            [1, 0],
+            [1, 1],
+            [0, 1],
+            [0, 2],
+            [1, 2],
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn gray_degenerate() {
+        assert_eq!(vec![Vec::<usize>::new()], iterate_generator(GrayEnumerator::new(&[])));
+        assert_eq!(inner_array_to_vec(vec![[0, 0], [0, 1]]), iterate_generator(GrayEnumerator::new(&[1, 2])));
+        assert!(iterate_generator(GrayEnumerator::new(&[3, 0])).is_empty());
+    }
+
+    #[test]
+    fn minimal_change() {
+        for cardinalities in [&[2, 3, 4][..], &[3, 1, 2, 3], &[4, 4]] {
+            let count = count_states(cardinalities);
+            let mut enumerator = GrayEnumerator::new(cardinalities);
+            assert_eq!((count, Some(count)), enumerator.size_hint());
+            assert!(enumerator.changed().is_empty());
+            let mut outputs = vec![enumerator.read().to_vec()];
+            while enumerator.advance() {
+                let previous = outputs.last().unwrap();
+                let current = enumerator.read();
+                let differing = (0..cardinalities.len())
+                    .filter(|&index| previous[index] != current[index])
+                    .collect::<Vec<_>>();
+                assert_eq!(differing, enumerator.changed());
+                let index = differing[0];
+                assert_eq!(1, previous[index].abs_diff(current[index]));
+                assert_eq!(Some(count - outputs.len()), enumerator.remaining());
+                outputs.push(current.to_vec());
+            }
+            assert_eq!(count, outputs.len());
+            outputs.sort();
+            let mut expected_outputs = Enumerator::new(cardinalities).into_vec();
+            expected_outputs.sort();
+            assert_eq!(expected_outputs, outputs);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! A streaming combiner of <sup>n</sup>C<sub>r</sub> combinations with repetition (multisets),
+//! in lexicographic order.
+//!
+//! For example, in a traversal of 3 ordinals over 2 positions, the initial combination `[0, 0]`
+//! is succeeded by `[0, 1]`, `[0, 2]`, `[1, 1]`, `[1, 2]` and `[2, 2]`.
+
+use crate::capture::CaptureMut;
+use crate::comb::{checked_count_multisets, exact_size_hint};
+use crate::stream::generator::Generator;
+
+#[derive(Debug)]
+pub struct MultisetCombiner<'a> {
+    n: usize,
+    ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    position: usize,
+    count: Option<usize>,
+}
+
+impl<'a> MultisetCombiner<'a> {
+    #[inline]
+    pub fn alloc(r: usize) -> CaptureMut<'a, Vec<usize>, [usize]> {
+        vec![0; r].into()
+    }
+
+    #[inline]
+    pub fn new(n: usize, r: usize) -> Self {
+        Self::new_no_alloc(n, Self::alloc(r))
+    }
+
+    #[inline]
+    pub fn new_no_alloc(n: usize, mut ordinals: CaptureMut<'a, Vec<usize>, [usize]>) -> Self {
+        debug_assert!(n > 0 || ordinals.is_empty(), "n must be greater than 0 for a non-zero r");
+        ordinals.fill(0);
+        let count = checked_count_multisets(n, ordinals.len());
+        Self {
+            n,
+            ordinals,
+            position: 0,
+            count,
+        }
+    }
+
+    /// The number of combinations advanced over so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of combinations yet to be read, counting the current one, or `None` if the
+    /// total number of combinations cannot be represented in a `usize`.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.count.map(|count| count - self.position)
+    }
+}
+
+impl Generator for MultisetCombiner<'_> {
+    type Item = [usize];
+
+    #[inline]
+    fn read(&self) -> &Self::Item {
+        &self.ordinals
+    }
+
+    #[inline]
+    fn advance(&mut self) -> bool {
+        let Some(caret) = self.ordinals.iter().rposition(|&ordinal| ordinal < self.n - 1) else {
+            return false;
+        };
+        let ordinal = self.ordinals[caret] + 1;
+        self.ordinals[caret..].fill(ordinal);
+        self.position += 1;
+        true
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comb::multiset_combiner::MultisetCombiner;
+    use crate::comb::tests::{inner_array_to_vec, iterate_generator};
+    use crate::stream::generator::Generator;
+
+    #[test]
+    fn multiset_3c0() {
+        let outputs = iterate_generator(MultisetCombiner::new(3, 0));
+        let expected_outputs = vec![
+            []
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn multiset_1c3() {
+        let outputs = iterate_generator(MultisetCombiner::new(1, 3));
+        let expected_outputs = vec![
+            [0, 0, 0]
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn multiset_3c2() {
+        let combiner = MultisetCombiner::new(3, 2);
+        assert_eq!((6, Some(6)), combiner.size_hint());
+        let outputs = iterate_generator(combiner);
+        let expected_outputs = vec![
+            [0, 0],
+            [0, 1],
+            [0, 2],
+            [1, 1],
+            [1, 2],
+            [2, 2],
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn multiset_4c3_size_hint() {
+        let mut combiner = MultisetCombiner::new(4, 3);
+        let mut remaining = 20;
+        loop {
+            assert_eq!((remaining, Some(remaining)), combiner.size_hint());
+            remaining -= 1;
+            if !combiner.advance() {
+                break;
+            }
+        }
+        assert_eq!(0, remaining);
+    }
+
+    #[test]
+    fn multiset_overflow() {
+        let mut combiner = MultisetCombiner::new(40, 30);
+        assert_eq!(None, combiner.remaining());
+        assert_eq!((usize::MAX, None), combiner.size_hint());
+        assert!(combiner.advance());
+        assert_eq!(1, combiner.position());
+        assert_eq!(Some(10), MultisetCombiner::new(3, 3).remaining());
+    }
+}
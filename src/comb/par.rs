@@ -0,0 +1,386 @@
+//! Chunked parallel folds over indexable combinatorial spaces.
+//!
+//! A [`Space`] maps each index in `0..len` to a tuple of ordinals, as do [`pick_state`],
+//! [`pick_state_hyper`], [`Permuter`] and [`Combiner`]. [`fold_par_no_alloc`] splits the index
+//! range into as many contiguous chunks as there are allocations, folding each chunk into its own
+//! [`Alloc`] on its own thread. Only the first tuple of each chunk is unranked; the rest are
+//! advanced to from their predecessors. Since each chunk is folded in index order and the
+//! allocations are returned in chunk order, merging the partial accumulators in the order of the
+//! allocations is deterministic, irrespective of how the threads are scheduled.
+//!
+//! [`split_par_no_alloc`] is the underlying primitive, handing each thread its range of indices
+//! and its allocation, for traversals that do not visit every index in their range.
+
+use crate::capture::CaptureMut;
+use crate::comb::bitmap::Bitmap;
+use crate::comb::combiner::Combiner;
+use crate::comb::permuter::{self, Permuter};
+use crate::comb::{
+    checked_count_combinations, checked_count_permutations, checked_count_states, pick_state,
+    pick_state_hyper,
+};
+use crate::stream::generator::Generator;
+use std::ops::Range;
+use std::thread;
+
+/// A space of ordinal tuples, indexable in `0..len`.
+pub trait Space: Sync {
+    /// Per-thread scratch space required by [`visit`](Self::visit).
+    type Scratch: Send;
+
+    /// The number of tuples in the space.
+    fn len(&self) -> usize;
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of ordinals in each tuple.
+    fn width(&self) -> usize;
+
+    fn scratch(&self) -> Self::Scratch;
+
+    /// Passes the tuples at the indices in the non-empty `range` to `f` in index order, alongside
+    /// their indices, unranking the tuple at the start of the range into `ordinals` and advancing
+    /// from there.
+    fn visit(
+        &self,
+        range: Range<usize>,
+        scratch: &mut Self::Scratch,
+        ordinals: &mut [usize],
+        f: impl FnMut(usize, &[usize]),
+    );
+}
+
+/// Advances `ordinals` to the succeeding state, the first ordinal varying fastest.
+#[inline(always)]
+fn next_state(ordinals: &mut [usize], cardinality: impl Fn(usize) -> usize) {
+    for (index, ordinal) in ordinals.iter_mut().enumerate() {
+        *ordinal += 1;
+        if *ordinal < cardinality(index) {
+            return;
+        }
+        *ordinal = 0;
+    }
+}
+
+/// The states of a mixed-radix space, indexed as per [`pick_state`].
+#[derive(Debug, Clone, Copy)]
+pub struct States<'a> {
+    pub cardinalities: &'a [usize],
+}
+
+impl Space for States<'_> {
+    type Scratch = ();
+
+    #[inline]
+    fn len(&self) -> usize {
+        checked_count_states(self.cardinalities).expect("number of states overflows usize")
+    }
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.cardinalities.len()
+    }
+
+    #[inline]
+    fn scratch(&self) {}
+
+    #[inline]
+    fn visit(&self, range: Range<usize>, _: &mut (), ordinals: &mut [usize], mut f: impl FnMut(usize, &[usize])) {
+        pick_state(self.cardinalities, range.start, ordinals);
+        for index in range {
+            f(index, ordinals);
+            next_state(ordinals, |dimension| self.cardinalities[dimension]);
+        }
+    }
+}
+
+/// The states of a space with a uniform `cardinality` in every dimension, indexed as per
+/// [`pick_state_hyper`].
+#[derive(Debug, Clone, Copy)]
+pub struct HyperStates {
+    pub cardinality: usize,
+    pub dimensions: usize,
+}
+
+impl Space for HyperStates {
+    type Scratch = ();
+
+    #[inline]
+    fn len(&self) -> usize {
+        u32::try_from(self.dimensions)
+            .ok()
+            .and_then(|dimensions| self.cardinality.checked_pow(dimensions))
+            .expect("number of states overflows usize")
+    }
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.dimensions
+    }
+
+    #[inline]
+    fn scratch(&self) {}
+
+    #[inline]
+    fn visit(&self, range: Range<usize>, _: &mut (), ordinals: &mut [usize], mut f: impl FnMut(usize, &[usize])) {
+        pick_state_hyper(self.cardinality, self.dimensions, range.start, ordinals);
+        for index in range {
+            f(index, ordinals);
+            next_state(ordinals, |_| self.cardinality);
+        }
+    }
+}
+
+/// The <sup>n</sup>P<sub>r</sub> permutations, indexed lexicographically in the order of
+/// [`Permuter`].
+#[derive(Debug, Clone, Copy)]
+pub struct Permutations {
+    pub n: usize,
+    pub r: usize,
+}
+
+impl Space for Permutations {
+    /// The bitmap of the [`Permuter`].
+    type Scratch = Bitmap;
+
+    #[inline]
+    fn len(&self) -> usize {
+        checked_count_permutations(self.n, self.r).expect("number of permutations overflows usize")
+    }
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.r
+    }
+
+    #[inline]
+    fn scratch(&self) -> Bitmap {
+        Bitmap::empty(self.n)
+    }
+
+    #[inline]
+    fn visit(&self, range: Range<usize>, bitmap: &mut Bitmap, ordinals: &mut [usize], mut f: impl FnMut(usize, &[usize])) {
+        let mut permuter = Permuter::new_at_no_alloc(
+            self.r,
+            range.start,
+            permuter::Alloc {
+                bitmap: CaptureMut::Borrowed(bitmap),
+                ordinals: CaptureMut::Borrowed(ordinals),
+            },
+        );
+        for index in range {
+            f(index, permuter.read());
+            permuter.advance();
+        }
+    }
+}
+
+/// The <sup>n</sup>C<sub>r</sub> combinations, indexed lexicographically in the order of
+/// [`Combiner`].
+#[derive(Debug, Clone, Copy)]
+pub struct Combinations {
+    pub n: usize,
+    pub r: usize,
+}
+
+impl Space for Combinations {
+    type Scratch = ();
+
+    #[inline]
+    fn len(&self) -> usize {
+        checked_count_combinations(self.n, self.r).expect("number of combinations overflows usize")
+    }
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.r
+    }
+
+    #[inline]
+    fn scratch(&self) {}
+
+    #[inline]
+    fn visit(&self, range: Range<usize>, _: &mut (), ordinals: &mut [usize], mut f: impl FnMut(usize, &[usize])) {
+        let mut combiner = Combiner::new_at_no_alloc(self.n, range.start, CaptureMut::Borrowed(ordinals));
+        for index in range {
+            f(index, combiner.read());
+            combiner.advance();
+        }
+    }
+}
+
+/// Splits `0..len` into `parts` contiguous ranges, differing in length by at most one.
+pub fn split_range(len: usize, parts: usize) -> impl Iterator<Item = Range<usize>> {
+    assert!(parts > 0, "number of parts must be greater than 0");
+    let (quotient, remainder) = (len / parts, len % parts);
+    (0..parts).map(move |part| {
+        let start = part * quotient + part.min(remainder);
+        let end = start + quotient + usize::from(part < remainder);
+        start..end
+    })
+}
+
+/// The allocation of a single chunk: the scratch space and ordinals used to pick tuples, and the
+/// accumulator into which they are folded.
+#[derive(Debug)]
+pub struct Alloc<'a, T, A> {
+    pub scratch: T,
+    pub ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    pub acc: A,
+}
+
+impl<T, A> Alloc<'_, T, A> {
+    #[inline]
+    pub fn new<S: Space<Scratch = T>>(space: &S, acc: A) -> Self {
+        Self {
+            scratch: space.scratch(),
+            ordinals: vec![0; space.width()].into(),
+            acc,
+        }
+    }
+}
+
+/// Folds the tuples in the given `range` of indices into the accumulator of `alloc`, in index
+/// order, passing each index alongside its tuple.
+#[inline]
+pub fn fold_range_no_alloc<S: Space, A>(
+    space: &S,
+    range: Range<usize>,
+    alloc: &mut Alloc<S::Scratch, A>,
+    mut f: impl FnMut(&mut A, usize, &[usize]),
+) {
+    let Alloc { scratch, ordinals, acc } = alloc;
+    debug_assert_eq!(space.width(), ordinals.len(), "length of ordinals must equal the width of the space");
+    if !range.is_empty() {
+        space.visit(range, scratch, ordinals, |index, ordinals| f(acc, index, ordinals));
+    }
+}
+
+/// Splits `0..len` into as many contiguous ranges as there are `allocs`, passing each range
+/// alongside its allocation to `f` on its own thread.
+pub fn split_par_no_alloc<A: Send>(len: usize, allocs: &mut [A], f: impl Fn(Range<usize>, &mut A) + Sync) {
+    assert!(!allocs.is_empty(), "at least one allocation is required, one for each thread");
+    let ranges = split_range(len, allocs.len());
+    let f = &f;
+    thread::scope(|scope| {
+        for (alloc, range) in allocs.iter_mut().zip(ranges) {
+            scope.spawn(move || f(range, alloc));
+        }
+    });
+}
+
+/// Folds the entire space on as many threads as there are `allocs`, the chunk of each thread
+/// being contiguous and following that of its predecessor.
+pub fn fold_par_no_alloc<S: Space, A: Send>(
+    space: &S,
+    allocs: &mut [Alloc<S::Scratch, A>],
+    f: impl Fn(&mut A, usize, &[usize]) + Sync,
+) {
+    split_par_no_alloc(space.len(), allocs, |range, alloc| fold_range_no_alloc(space, range, alloc, &f));
+}
+
+/// Folds the entire space on `threads` threads, each starting with an accumulator from `init`,
+/// and merges the partial accumulators in chunk order.
+pub fn fold_par<S: Space, A: Send>(
+    space: &S,
+    threads: usize,
+    init: impl Fn() -> A,
+    f: impl Fn(&mut A, usize, &[usize]) + Sync,
+    merge: impl FnMut(A, A) -> A,
+) -> A {
+    assert!(threads > 0, "number of threads must be greater than 0");
+    let mut allocs = (0..threads)
+        .map(|_| Alloc::new(space, init()))
+        .collect::<Vec<_>>();
+    fold_par_no_alloc(space, &mut allocs, f);
+    allocs.into_iter().map(|alloc| alloc.acc).reduce(merge).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comb::enumerator::Enumerator;
+    use crate::comb::tests::iterate_generator;
+    use crate::stream::itemiser::Itemiser;
+
+    fn collect_par<S: Space>(space: &S, threads: usize) -> Vec<Vec<usize>> {
+        fold_par(
+            space,
+            threads,
+            Vec::new,
+            |acc, index, ordinals| {
+                assert_eq!(acc.len() + acc.first().map_or(index, |(first, _)| *first), index);
+                acc.push((index, ordinals.to_vec()));
+            },
+            |mut left, right| {
+                left.extend(right);
+                left
+            },
+        )
+        .into_iter()
+        .map(|(_, ordinals)| ordinals)
+        .collect()
+    }
+
+    #[test]
+    fn split_range_even() {
+        assert_eq!(vec![0..2, 2..4, 4..6], split_range(6, 3).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn split_range_uneven() {
+        assert_eq!(vec![0..3, 3..5, 5..7], split_range(7, 3).collect::<Vec<_>>());
+        assert_eq!(vec![0..1, 1..2, 2..2, 2..2], split_range(2, 4).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn states_in_order() {
+        let cardinalities = [2, 3, 4];
+        let expected = Enumerator::new(&cardinalities).into_vec();
+        for threads in [1, 3, 5, 30] {
+            assert_eq!(expected, collect_par(&States { cardinalities: &cardinalities }, threads));
+        }
+        assert_eq!(expected.len(), States { cardinalities: &cardinalities }.len());
+    }
+
+    #[test]
+    fn hyper_states_in_order() {
+        let expected = Enumerator::new(&[3, 3]).into_vec();
+        assert_eq!(expected, collect_par(&HyperStates { cardinality: 3, dimensions: 2 }, 4));
+    }
+
+    #[test]
+    fn permutations_in_order() {
+        let expected = iterate_generator(Permuter::new(5, 3));
+        assert_eq!(expected, collect_par(&Permutations { n: 5, r: 3 }, 7));
+        assert_eq!(vec![Vec::<usize>::new()], collect_par(&Permutations { n: 5, r: 0 }, 2));
+    }
+
+    #[test]
+    fn combinations_in_order() {
+        let expected = iterate_generator(Combiner::new(6, 3));
+        assert_eq!(expected, collect_par(&Combinations { n: 6, r: 3 }, 4));
+        assert_eq!(vec![Vec::<usize>::new()], collect_par(&Combinations { n: 6, r: 0 }, 2));
+    }
+
+    #[test]
+    fn merge_is_deterministic() {
+        let space = Permutations { n: 8, r: 4 };
+        let sum = |threads| {
+            fold_par(
+                &space,
+                threads,
+                || 0.0,
+                |acc, _, ordinals| *acc += 1.0 / (1.0 + ordinals.iter().sum::<usize>() as f64),
+                |left, right| left + right,
+            )
+        };
+        let expected = sum(3);
+        for _ in 0..10 {
+            assert_eq!(expected.to_bits(), sum(3).to_bits());
+        }
+    }
+}
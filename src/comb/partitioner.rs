@@ -0,0 +1,152 @@
+//! A streaming generator of set partitions, encoded as restricted growth strings.
+//!
+//! Each element is assigned the index of its block, such that the first element is in block 0
+//! and every subsequent element is in an existing block or the block immediately after the
+//! highest so far. For example, the partitions of 3 elements are traversed as `[0, 0, 0]`,
+//! `[0, 0, 1]`, `[0, 1, 0]`, `[0, 1, 1]` and `[0, 1, 2]`.
+
+use crate::capture::CaptureMut;
+use crate::comb::{checked_count_set_partitions_no_alloc, exact_size_hint};
+use crate::stream::generator::Generator;
+
+#[derive(Debug)]
+pub struct Alloc<'a> {
+    pub blocks: CaptureMut<'a, Vec<usize>, [usize]>,
+
+    /// The highest block index over each prefix of `blocks`.
+    pub maxima: CaptureMut<'a, Vec<usize>, [usize]>,
+}
+
+impl Alloc<'_> {
+    #[inline]
+    pub fn new(n: usize) -> Self {
+        Self {
+            blocks: vec![0; n].into(),
+            maxima: vec![0; n].into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Partitioner<'a> {
+    blocks: CaptureMut<'a, Vec<usize>, [usize]>,
+    maxima: CaptureMut<'a, Vec<usize>, [usize]>,
+    position: usize,
+    count: Option<usize>,
+}
+
+impl<'a> Partitioner<'a> {
+    #[inline]
+    pub fn new(n: usize) -> Self {
+        Self::new_no_alloc(Alloc::new(n))
+    }
+
+    #[inline]
+    pub fn new_no_alloc(alloc: Alloc<'a>) -> Self {
+        let Alloc { mut blocks, mut maxima } = alloc;
+        debug_assert_eq!(blocks.len(), maxima.len(), "length of maxima must equal the length of blocks");
+        let count = checked_count_set_partitions_no_alloc(&mut maxima);
+        blocks.fill(0);
+        maxima.fill(0);
+        Self {
+            blocks,
+            maxima,
+            position: 0,
+            count,
+        }
+    }
+
+    /// The number of partitions advanced over so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of partitions yet to be read, counting the current one, or `None` if the
+    /// total number of partitions cannot be represented in a `usize`.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.count.map(|count| count - self.position)
+    }
+}
+
+impl Generator for Partitioner<'_> {
+    type Item = [usize];
+
+    #[inline]
+    fn read(&self) -> &Self::Item {
+        &self.blocks
+    }
+
+    #[inline]
+    fn advance(&mut self) -> bool {
+        let Some(caret) = (1..self.blocks.len())
+            .rev()
+            .find(|&index| self.blocks[index] <= self.maxima[index - 1])
+        else {
+            return false;
+        };
+        self.blocks[caret] += 1;
+        self.maxima[caret] = self.maxima[caret - 1].max(self.blocks[caret]);
+        let maximum = self.maxima[caret];
+        self.blocks[caret + 1..].fill(0);
+        self.maxima[caret + 1..].fill(maximum);
+        self.position += 1;
+        true
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comb::count_set_partitions;
+    use crate::comb::partitioner::Partitioner;
+    use crate::comb::tests::{inner_array_to_vec, iterate_generator};
+    use crate::stream::generator::Generator;
+
+    #[test]
+    fn partition_0() {
+        let outputs = iterate_generator(Partitioner::new(0));
+        let expected_outputs = vec![
+            []
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn partition_3() {
+        let partitioner = Partitioner::new(3);
+        assert_eq!((5, Some(5)), partitioner.size_hint());
+        let outputs = iterate_generator(partitioner);
+        let expected_outputs = vec![
+            [0, 0, 0],
+            [0, 0, 1],
+            [0, 1, 0],
+            [0, 1, 1],
+            [0, 1, 2],
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn count_matches_traversal() {
+        for (n, bell) in [(1, 1), (2, 2), (4, 15), (5, 52), (7, 877)] {
+            assert_eq!(bell, count_set_partitions(n));
+            assert_eq!(bell, iterate_generator(Partitioner::new(n)).len());
+        }
+    }
+
+    #[test]
+    fn partition_overflow() {
+        let mut partitioner = Partitioner::new(26);
+        assert_eq!(None, partitioner.remaining());
+        assert_eq!((usize::MAX, None), partitioner.size_hint());
+        assert!(partitioner.advance());
+        assert_eq!(1, partitioner.position());
+        assert_eq!(Some(5), Partitioner::new(3).remaining());
+    }
+}
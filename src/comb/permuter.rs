@@ -6,11 +6,13 @@
 //! the lowest ordinals anchored for as long as possible.
 
 use crate::capture::CaptureMut;
+use crate::comb::bitmap::Bitmap;
+use crate::comb::{checked_count_permutations, exact_size_hint};
 use crate::stream::generator::Generator;
 
 #[derive(Debug)]
 pub struct Alloc<'a> {
-    pub bitmap: CaptureMut<'a, Vec<bool>, [bool]>,
+    pub bitmap: CaptureMut<'a, Bitmap>,
     pub ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
 }
 
@@ -18,7 +20,7 @@ impl Alloc<'_> {
     #[inline]
     pub fn new(n: usize, r: usize) -> Self {
         Self {
-            bitmap: vec![false; n].into(),
+            bitmap: Bitmap::empty(n).into(),
             ordinals: vec![0; r].into(),
         }
     }
@@ -27,8 +29,10 @@ impl Alloc<'_> {
 #[derive(Debug)]
 pub struct Permuter<'a> {
     r: usize,
-    bitmap: CaptureMut<'a, Vec<bool>, [bool]>,
+    bitmap: CaptureMut<'a, Bitmap>,
     ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    position: usize,
+    count: Option<usize>,
 }
 
 impl<'a> Permuter<'a> {
@@ -43,40 +47,91 @@ impl<'a> Permuter<'a> {
         debug_assert_eq!(r, ordinals.len(), "length of ordinals must equal r");
         debug_assert!(bitmap.len() >= r, "length of bitmap must be greater or equal to r");
         
+        bitmap.clear_all();
         for (index, ordinal) in ordinals.iter_mut().enumerate() {
             *ordinal = index;
-            bitmap[*ordinal] = true;
+            bitmap.set(index);
         }
-        for i in ordinals.len()..bitmap.len() {
-            bitmap[i] = false;
+
+        let count = checked_count_permutations(bitmap.len(), r);
+        Self {
+            r,
+            bitmap,
+            ordinals,
+            position: 0,
+            count,
         }
-        for ordinal in 0..ordinals.len() {
-            ordinals[ordinal] = ordinal;
+    }
+
+    /// Creates a permuter positioned at the permutation with the given lexicographic `index`,
+    /// such that the traversal resumes from that permutation.
+    #[inline]
+    pub fn new_at(n: usize, r: usize, index: usize) -> Self {
+        Self::new_at_no_alloc(r, index, Alloc::new(n, r))
+    }
+
+    #[inline]
+    pub fn new_at_no_alloc(r: usize, index: usize, alloc: Alloc<'a>) -> Self {
+        let Alloc { mut bitmap, mut ordinals } = alloc;
+        debug_assert_eq!(r, ordinals.len(), "length of ordinals must equal r");
+        debug_assert!(bitmap.len() >= r, "length of bitmap must be greater or equal to r");
+
+        let n = bitmap.len();
+        let count = checked_count_permutations(n, r);
+        assert!(
+            count.is_none_or(|count| index < count),
+            "index {index} is out of bounds for {n}P{r}"
+        );
+        // the digit of each position counts the permutations of the positions that follow it; a
+        // radix that overflows usize exceeds any index, so its digit is zero
+        bitmap.clear_all();
+        let mut residual = index;
+        for (position, ordinal) in ordinals.iter_mut().enumerate() {
+            let digit = match checked_count_permutations(n - position - 1, r - position - 1) {
+                Some(radix) => {
+                    let digit = residual / radix;
+                    residual %= radix;
+                    digit
+                }
+                None => 0,
+            };
+            *ordinal = bitmap.select_free(digit).unwrap();
+            bitmap.set(*ordinal);
         }
 
         Self {
             r,
             bitmap,
             ordinals,
+            position: index,
+            count,
         }
     }
+
+    /// The number of permutations advanced over so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of permutations yet to be read, counting the current one, or `None` if the
+    /// total number of permutations cannot be represented in a `usize`.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.count.map(|count| count - self.position)
+    }
 }
 
 #[inline(always)]
-fn take_next_available(bitmap: &mut [bool], min: usize) -> Option<usize> {
-    for b in min..bitmap.len() {
-        if bitmap[b] {
-            continue;
-        }
-        bitmap[b] = true;
-        return Some(b);
-    }
-    None
+fn take_next_available(bitmap: &mut Bitmap, min: usize) -> Option<usize> {
+    let next_free = bitmap.next_free(min)?;
+    bitmap.set(next_free);
+    Some(next_free)
 }
 
 #[inline(always)]
-fn is_full(bitmap: &[bool], min: usize) -> bool {
-    bitmap[min..].iter().all(|b| *b)
+fn is_full(bitmap: &Bitmap, min: usize) -> bool {
+    bitmap.next_free(min).is_none()
 }
 
 impl Generator for Permuter<'_> {
@@ -97,7 +152,7 @@ impl Generator for Permuter<'_> {
         loop {
             let ordinal = self.ordinals[caret];
             if is_full(&self.bitmap, ordinal + 1) {
-                self.bitmap[ordinal] = false;
+                self.bitmap.clear(ordinal);
                 if caret != 0 {
                     caret -= 1;
                 } else {
@@ -110,21 +165,28 @@ impl Generator for Permuter<'_> {
 
         let next_available =
             take_next_available(&mut self.bitmap, self.ordinals[caret] + 1).unwrap();
-        self.bitmap[self.ordinals[caret]] = false;
+        self.bitmap.clear(self.ordinals[caret]);
         self.ordinals[caret] = next_available;
 
         for index in caret + 1..self.r {
             self.ordinals[index] = take_next_available(&mut self.bitmap, 0).unwrap()
         }
+        self.position += 1;
 
         true
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::comb::permuter::Permuter;
     use crate::comb::tests::{inner_array_to_vec, iterate_generator};
+    use crate::stream::generator::Generator;
 
     #[test]
     fn permuter_0p0() {
@@ -257,4 +319,38 @@ mod tests {
         ];
         assert_eq!(inner_array_to_vec(expected_outputs), outputs);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn resume_at_position() {
+        for (n, r) in [(4, 0), (4, 2), (4, 4), (6, 3)] {
+            let outputs = iterate_generator(Permuter::new(n, r));
+            for (index, expected) in outputs.iter().enumerate() {
+                let permuter = Permuter::new_at(n, r, index);
+                assert_eq!(index, permuter.position());
+                assert_eq!(Some(outputs.len() - index), permuter.remaining());
+                assert_eq!(outputs[index..], iterate_generator(permuter));
+                assert_eq!(expected, Permuter::new_at(n, r, index).read());
+            }
+        }
+
+        let permuter = Permuter::new_at(30, 30, 2);
+        let mut expected = (0..30).collect::<Vec<_>>();
+        expected[27..].copy_from_slice(&[28, 27, 29]);
+        assert_eq!(expected, permuter.read());
+    }
+
+    #[test]
+    fn permuter_exact_size() {
+        let mut permuter = Permuter::new(4, 2);
+        for position in 0..12 {
+            assert_eq!(position, permuter.position());
+            assert_eq!(Some(12 - position), permuter.remaining());
+            assert_eq!((12 - position, Some(12 - position)), permuter.size_hint());
+            assert_eq!(position < 11, permuter.advance());
+        }
+
+        let permuter = Permuter::new(30, 30);
+        assert_eq!(None, permuter.remaining());
+        assert_eq!((usize::MAX, None), permuter.size_hint());
+    }
+}
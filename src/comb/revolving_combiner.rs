@@ -0,0 +1,227 @@
+//! A streaming combiner of <sup>n</sup>C<sub>r</sub> combinations in revolving-door order, such
+//! that each successive combination differs from its predecessor by exactly one ordinal leaving
+//! and one entering. The ordinals of each combination are sorted in ascending order.
+//!
+//! For example, in a <sup>4</sup>C<sub>2</sub> traversal, the initial combination `[0, 1]` is
+//! succeeded by `[1, 2]`, `[0, 2]`, `[2, 3]`, `[1, 3]` and `[0, 3]`.
+//!
+//! The implementation follows Algorithm R of Knuth, <i>The Art of Computer Programming</i>,
+//! Volume 4A, §7.2.1.3, changing at most two positions of the sorted combination per step.
+
+use crate::capture::CaptureMut;
+use crate::comb::{checked_count_combinations, exact_size_hint};
+use crate::stream::generator::Generator;
+
+#[derive(Debug)]
+pub struct RevolvingCombiner<'a> {
+    r: usize,
+    /// The combination, followed by `n` as a sentinel.
+    ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    changed: [usize; 2],
+    changes: usize,
+    position: usize,
+    count: Option<usize>,
+}
+
+impl<'a> RevolvingCombiner<'a> {
+    #[inline]
+    pub fn alloc(r: usize) -> CaptureMut<'a, Vec<usize>, [usize]> {
+        vec![0; r + 1].into()
+    }
+
+    #[inline]
+    pub fn new(n: usize, r: usize) -> Self {
+        Self::new_no_alloc(n, Self::alloc(r))
+    }
+
+    /// Creates a combiner over `ordinals`, whose length must be `r + 1`. If `r` exceeds `n`, there
+    /// are no combinations and the traversal is empty.
+    #[inline]
+    pub fn new_no_alloc(n: usize, mut ordinals: CaptureMut<'a, Vec<usize>, [usize]>) -> Self {
+        debug_assert!(!ordinals.is_empty(), "length of ordinals must be r + 1");
+        let r = ordinals.len() - 1;
+        for (index, ordinal) in ordinals[..r].iter_mut().enumerate() {
+            *ordinal = index;
+        }
+        ordinals[r] = n;
+        let count = checked_count_combinations(n, r);
+        Self {
+            r,
+            ordinals,
+            changed: [0; 2],
+            changes: 0,
+            position: 0,
+            count,
+        }
+    }
+
+    /// The positions of the ordinals changed by the last advance; empty prior to the first.
+    #[inline]
+    pub fn changed(&self) -> &[usize] {
+        &self.changed[..self.changes]
+    }
+
+    /// The number of combinations advanced over so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of combinations yet to be read, counting the current one, or `None` if the
+    /// total number of combinations cannot be represented in a `usize`.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.count.map(|count| count - self.position)
+    }
+
+    /// Records a change to the first position alone.
+    #[inline(always)]
+    fn changed_first(&mut self) -> bool {
+        self.changed[0] = 0;
+        self.changes = 1;
+        self.position += 1;
+        true
+    }
+
+    /// Records a change to the pair of positions `caret - 1` and `caret`, omitting the former
+    /// if its ordinal was unchanged.
+    #[inline(always)]
+    fn changed_pair(&mut self, caret: usize, previous: usize) -> bool {
+        self.changes = 0;
+        if self.ordinals[caret - 1] != previous {
+            self.changed[0] = caret - 1;
+            self.changes = 1;
+        }
+        self.changed[self.changes] = caret;
+        self.changes += 1;
+        self.position += 1;
+        true
+    }
+}
+
+impl Generator for RevolvingCombiner<'_> {
+    type Item = [usize];
+
+    #[inline]
+    fn read(&self) -> &Self::Item {
+        &self.ordinals[..self.r]
+    }
+
+    #[inline]
+    fn advance(&mut self) -> bool {
+        let r = self.r;
+        if r == 0 || self.count == Some(0) {
+            return false;
+        }
+
+        // the one-based caret j of Algorithm R corresponds to the position j - 1
+        let mut caret = 1;
+        let mut decrease = if r % 2 == 1 {
+            if self.ordinals[0] + 1 < self.ordinals[1] {
+                self.ordinals[0] += 1;
+                return self.changed_first();
+            }
+            true
+        } else {
+            if self.ordinals[0] > 0 {
+                self.ordinals[0] -= 1;
+                return self.changed_first();
+            }
+            false
+        };
+
+        while caret < r {
+            if decrease {
+                // try to decrease the ordinal at the caret
+                if self.ordinals[caret] > caret {
+                    let previous = self.ordinals[caret - 1];
+                    self.ordinals[caret] = previous;
+                    self.ordinals[caret - 1] = caret - 1;
+                    return self.changed_pair(caret, previous);
+                }
+            } else {
+                // try to increase the ordinal at the caret
+                if self.ordinals[caret] + 1 < self.ordinals[caret + 1] {
+                    let previous = self.ordinals[caret - 1];
+                    self.ordinals[caret - 1] = self.ordinals[caret];
+                    self.ordinals[caret] += 1;
+                    return self.changed_pair(caret, previous);
+                }
+            }
+            caret += 1;
+            decrease = !decrease;
+        }
+        false
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comb::combiner::Combiner;
+    use crate::comb::count_combinations;
+    use crate::comb::tests::{inner_array_to_vec, iterate_generator};
+
+    #[test]
+    fn revolving_4c2() {
+        let outputs = iterate_generator(RevolvingCombiner::new(4, 2));
+        let expected_outputs = vec![
+            [0, 1],
+            [1, 2],
+            [0, 2],
+            [2, 3],
+            [1, 3],
+            [0, 3],
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn revolving_degenerate() {
+        assert_eq!(vec![Vec::<usize>::new()], iterate_generator(RevolvingCombiner::new(0, 0)));
+        assert_eq!(vec![Vec::<usize>::new()], iterate_generator(RevolvingCombiner::new(3, 0)));
+        assert_eq!(inner_array_to_vec(vec![[0], [1], [2]]), iterate_generator(RevolvingCombiner::new(3, 1)));
+        assert_eq!(inner_array_to_vec(vec![[0, 1, 2]]), iterate_generator(RevolvingCombiner::new(3, 3)));
+    }
+
+    #[test]
+    fn revolving_r_exceeds_n() {
+        let mut combiner = RevolvingCombiner::new(2, 3);
+        assert_eq!(Some(0), combiner.remaining());
+        assert_eq!((0, Some(0)), combiner.size_hint());
+        assert!(!combiner.advance());
+        assert!(iterate_generator(RevolvingCombiner::new(2, 3)).is_empty());
+    }
+
+    #[test]
+    fn minimal_change() {
+        for (n, r) in [(5, 2), (6, 3), (7, 4), (8, 5), (6, 6)] {
+            let count = count_combinations(n, r);
+            let mut combiner = RevolvingCombiner::new(n, r);
+            assert_eq!((count, Some(count)), combiner.size_hint());
+            assert!(combiner.changed().is_empty());
+            let mut outputs = vec![combiner.read().to_vec()];
+            while combiner.advance() {
+                let previous = outputs.last().unwrap();
+                let current = combiner.read();
+                assert!(current.is_sorted_by(|a, b| a < b));
+                let differing = (0..r)
+                    .filter(|&index| previous[index] != current[index])
+                    .collect::<Vec<_>>();
+                assert_eq!(differing, combiner.changed());
+                let left = previous.iter().filter(|ordinal| !current.contains(ordinal)).count();
+                assert_eq!(1, left, "{previous:?} -> {current:?}");
+                assert_eq!(Some(count - outputs.len()), combiner.remaining());
+                outputs.push(current.to_vec());
+            }
+            assert_eq!(count, outputs.len());
+            outputs.sort();
+            assert_eq!(iterate_generator(Combiner::new(n, r)), outputs);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Uniform and weighted random sampling of combinations and permutations, without enumerating
+//! the underlying space.
+//!
+//! The length of `ordinals` determines the number of elements sampled, and `bitmap` (of length
+//! `n`) is used as scratch space for tracking the elements drawn so far.
+
+use crate::comb::bitmap::Bitmap;
+use crate::random::uniform;
+use tinyrand::Rand;
+
+/// Draws a uniformly random <sup>n</sup>C<sub>r</sub> combination using Floyd's algorithm,
+/// writing its elements in ascending order.
+#[inline]
+pub fn sample_combination(rand: &mut impl Rand, n: usize, bitmap: &mut Bitmap, ordinals: &mut [usize]) {
+    let r = ordinals.len();
+    debug_assert!(r <= n, "r ({r}) cannot exceed n ({n})");
+    debug_assert_eq!(n, bitmap.len(), "bitmap length must equal n");
+    bitmap.clear_all();
+    for upper in n - r..n {
+        let candidate = rand.next_lim_usize(upper + 1);
+        if bitmap.get(candidate) {
+            bitmap.set(upper);
+        } else {
+            bitmap.set(candidate);
+        }
+    }
+    for (ordinal, element) in ordinals.iter_mut().zip(bitmap.ordinals()) {
+        *ordinal = element;
+    }
+}
+
+/// Draws a uniformly random <sup>n</sup>P<sub>r</sub> permutation.
+#[inline]
+pub fn sample_permutation(rand: &mut impl Rand, n: usize, bitmap: &mut Bitmap, ordinals: &mut [usize]) {
+    debug_assert!(ordinals.len() <= n, "r ({}) cannot exceed n ({n})", ordinals.len());
+    debug_assert_eq!(n, bitmap.len(), "bitmap length must equal n");
+    bitmap.clear_all();
+    for (index, ordinal) in ordinals.iter_mut().enumerate() {
+        *ordinal = bitmap.select_free(rand.next_lim_usize(n - index)).unwrap();
+        bitmap.set(*ordinal);
+    }
+}
+
+/// Draws a permutation of the elements `0..weights.len()` without replacement, selecting each
+/// successive element with probability proportional to its weight among the elements not yet
+/// drawn. Given normalised win probabilities as weights, this samples a finishing order under
+/// the Harville (Plackett–Luce) model.
+///
+/// Once the weight of the undrawn elements is exhausted, the remaining elements are drawn
+/// uniformly.
+#[inline]
+pub fn sample_weighted(rand: &mut impl Rand, weights: &[f64], bitmap: &mut Bitmap, ordinals: &mut [usize]) {
+    let n = weights.len();
+    debug_assert!(ordinals.len() <= n, "r ({}) cannot exceed n ({n})", ordinals.len());
+    debug_assert_eq!(n, bitmap.len(), "bitmap length must equal the number of weights");
+    debug_assert!(weights.iter().all(|&weight| weight >= 0.0), "weights cannot be negative");
+    bitmap.clear_all();
+    let mut remaining_weight = weights.iter().sum::<f64>();
+    for (index, ordinal) in ordinals.iter_mut().enumerate() {
+        *ordinal = if remaining_weight > 0.0 {
+            draw_weighted(rand, weights, bitmap, remaining_weight)
+        } else {
+            None
+        }
+        .unwrap_or_else(|| bitmap.select_free(rand.next_lim_usize(n - index)).unwrap());
+        bitmap.set(*ordinal);
+        remaining_weight -= weights[*ordinal];
+    }
+}
+
+/// Draws a free element in proportion to its weight, returning `None` if rounding leaves the
+/// draw beyond the last weighted element.
+#[inline(always)]
+fn draw_weighted(rand: &mut impl Rand, weights: &[f64], bitmap: &Bitmap, remaining_weight: f64) -> Option<usize> {
+    let mut threshold = uniform(rand) * remaining_weight;
+    let mut last_weighted = None;
+    for (element, &weight) in weights.iter().enumerate() {
+        if weight == 0.0 || bitmap.get(element) {
+            continue;
+        }
+        if threshold < weight {
+            return Some(element);
+        }
+        threshold -= weight;
+        last_weighted = Some(element);
+    }
+    last_weighted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comb::{count_combinations, rank_combination};
+    use crate::harville::classic;
+    use crate::matrix::Matrix;
+    use crate::testing::dilated_probs;
+    use tinyrand::{Seeded, StdRand};
+
+    const TRIALS: usize = 100_000;
+
+    #[test]
+    fn combinations_uniform() {
+        let (n, r) = (6, 3);
+        let mut rand = StdRand::seed(42);
+        let mut bitmap = Bitmap::empty(n);
+        let mut ordinals = [0; 3];
+        let mut counts = vec![0; count_combinations(n, r)];
+        for _ in 0..TRIALS {
+            sample_combination(&mut rand, n, &mut bitmap, &mut ordinals);
+            assert!(ordinals.is_sorted_by(|a, b| a < b));
+            counts[rank_combination(n, &ordinals)] += 1;
+        }
+        let expected = TRIALS as f64 / counts.len() as f64;
+        for count in counts {
+            assert!((count as f64 - expected).abs() < expected * 0.1, "count {count} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn permutations_uniform() {
+        let n = 5;
+        let mut rand = StdRand::seed(42);
+        let mut bitmap = Bitmap::empty(n);
+        let mut ordinals = [0; 2];
+        let mut counts = Matrix::<usize>::allocate(n, n);
+        for _ in 0..TRIALS {
+            sample_permutation(&mut rand, n, &mut bitmap, &mut ordinals);
+            assert_ne!(ordinals[0], ordinals[1]);
+            counts[(ordinals[0], ordinals[1])] += 1;
+        }
+        let expected = TRIALS as f64 / 20.0;
+        for first in 0..n {
+            for second in 0..n {
+                let count = counts[(first, second)] as f64;
+                if first == second {
+                    assert_eq!(0.0, count);
+                } else {
+                    assert!((count - expected).abs() < expected * 0.1, "count {count} vs {expected}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_matches_harville() {
+        let weights = [0.5, 0.25, 0.15, 0.1, 0.0];
+        let mut rand = StdRand::seed(42);
+        let mut bitmap = Bitmap::empty(weights.len());
+        let mut ordinals = [0; 5];
+        let mut counts = Matrix::<usize>::allocate(5, 5);
+        for _ in 0..TRIALS {
+            sample_weighted(&mut rand, &weights, &mut bitmap, &mut ordinals);
+            for (rank, &runner) in ordinals.iter().enumerate() {
+                counts[(rank, runner)] += 1;
+            }
+        }
+        let summary = classic::summary(&dilated_probs(&weights, 4));
+        for rank in 0..4 {
+            for runner in 0..4 {
+                let freq = counts[(rank, runner)] as f64 / TRIALS as f64;
+                assert!((freq - summary[(rank, runner)]).abs() < 0.01, "rank {rank}, runner {runner}: {freq}");
+            }
+        }
+        // the zero-weight element always finishes last
+        assert_eq!(TRIALS, counts[(4, 4)]);
+    }
+}
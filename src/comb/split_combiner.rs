@@ -62,6 +62,18 @@ impl<'a> SplitCombiner<'a> {
             borrowed: Split(ordinals_borrow, ordinals_len),
         }
     }
+
+    /// The number of combinations advanced over so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.ordinals.len() - self.borrowed.1
+    }
+
+    /// The number of combinations yet to be read, counting the current one.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.borrowed.1 + 1
+    }
 }
 
 impl<'a> Generator for SplitCombiner<'a> {
@@ -88,6 +100,12 @@ impl<'a> Generator for SplitCombiner<'a> {
             false
         }
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
 }
 
 #[cfg(test)]
@@ -169,4 +187,15 @@ mod tests {
 
         assert!(!combiners[0].as_mut().unwrap().advance());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn split_exact_size() {
+        let mut combiner = SplitCombiner::new(4);
+        for position in 0..4 {
+            assert_eq!(position, combiner.position());
+            assert_eq!(4 - position, combiner.remaining());
+            assert_eq!((4 - position, Some(4 - position)), combiner.size_hint());
+            assert_eq!(position < 3, combiner.advance());
+        }
+    }
+}
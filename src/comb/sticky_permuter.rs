@@ -10,14 +10,14 @@
 
 use crate::capture::CaptureMut;
 use crate::comb::combiner::Combiner;
+use crate::comb::{checked_count_permutations, exact_size_hint};
 use crate::stream::generator::Generator;
-use crate::comb::split_combiner::{Split, SplitCombiner};
 
 #[derive(Debug)]
 pub struct Alloc<'a> {
     pub combiner_ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
     pub whole_ordinals_stack: CaptureMut<'a, Vec<usize>, [usize]>,
-    pub split_ordinals_stack: CaptureMut<'a, Vec<usize>, [usize]>,
+    pub omitted: CaptureMut<'a, Vec<usize>, [usize]>,
     pub ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
 }
 
@@ -28,7 +28,7 @@ impl Alloc<'_> {
         Self {
             combiner_ordinals: vec![0; r].into(),
             whole_ordinals_stack: vec![0; stack_len].into(),
-            split_ordinals_stack: vec![0; stack_len].into(),
+            omitted: vec![0; r].into(),
             ordinals: vec![0; r].into(),
         }
     }
@@ -38,6 +38,16 @@ impl Alloc<'_> {
         (r + 1) * r / 2
     }
 
+    /// Shortens the constituents in place to suit a smaller `r`, without reallocating.
+    #[inline]
+    pub fn truncate(&mut self, smaller_r: usize) {
+        let stack_len = Self::stack_len(smaller_r);
+        self.combiner_ordinals.truncate(smaller_r);
+        self.whole_ordinals_stack.truncate(stack_len);
+        self.omitted.truncate(smaller_r);
+        self.ordinals.truncate(smaller_r);
+    }
+
     /// Takes sub-slices of the constituents to suit the needs of permuting
     /// over a smaller `r` than what was specified in the initial allocation,
     /// returning a borrowed projection of [`Self`].
@@ -56,143 +66,205 @@ impl Alloc<'_> {
             whole_ordinals_stack: CaptureMut::Borrowed(
                 &mut self.whole_ordinals_stack[..stack_len],
             ),
-            split_ordinals_stack: CaptureMut::Borrowed(
-                &mut self.split_ordinals_stack[..stack_len],
-            ),
+            omitted: CaptureMut::Borrowed(&mut self.omitted[..smaller_r]),
             ordinals: CaptureMut::Borrowed(&mut self.ordinals[..smaller_r]),
         }
     }
 }
 
-#[inline]
-pub fn permute(n: usize, r: usize, f: impl FnMut(&[usize]) -> bool) {
-    permute_no_alloc(n, r, Alloc::new(r), f)
+/// A resumable state machine traversing the permutations in the same order as [`permute`].
+///
+/// Each combination yielded by a [`Combiner`] is permuted by omitting one of its ordinals at a
+/// time, from the last to the first, and placing the omitted ordinal in the rightmost unfilled
+/// position; the remaining ordinals are permuted likewise. The state comprises the index of the
+/// omitted ordinal at each depth, and the ordinals remaining at each depth (held in the whole
+/// ordinals stack, one frame per depth).
+#[derive(Debug)]
+pub struct StickyPermuter<'a> {
+    r: usize,
+    combiner: Combiner<'a>,
+    frames: CaptureMut<'a, Vec<usize>, [usize]>,
+    omitted: CaptureMut<'a, Vec<usize>, [usize]>,
+    ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    position: usize,
+    count: Option<usize>,
 }
 
-#[inline]
-pub fn permute_no_alloc(n: usize, r: usize, alloc: Alloc, mut f: impl FnMut(&[usize]) -> bool) {
-    let Alloc {
-        combiner_ordinals,
-        mut whole_ordinals_stack,
-        mut split_ordinals_stack,
-        mut ordinals,
-    } = alloc;
-
-    let expected_stack_len = Alloc::stack_len(r);
-    debug_assert_eq!(
-        combiner_ordinals.len(),
-        r,
-        "combiner ordinals length must equal r"
-    );
-    debug_assert_eq!(
-        whole_ordinals_stack.len(),
-        expected_stack_len,
-        "whole ordinals stack length must equal r(r+1)/2"
-    );
-    debug_assert_eq!(
-        split_ordinals_stack.len(),
-        expected_stack_len,
-        "split ordinals stack length must equal r(r+1)/2"
-    );
-    debug_assert_eq!(ordinals.len(), r, "ordinals length must equal r");
-
-    let mut combiner = Combiner::new_no_alloc(n, combiner_ordinals);
-    loop {
-        //println!("combination: {:?}", combiner.ordinals());
-        for (index, ordinal) in combiner.read().iter().enumerate() {
-            whole_ordinals_stack[index] = *ordinal;
+impl<'a> StickyPermuter<'a> {
+    #[inline]
+    pub fn new(n: usize, r: usize) -> Self {
+        Self::new_no_alloc(n, r, Alloc::new(r))
+    }
+
+    #[inline]
+    pub fn new_no_alloc(n: usize, r: usize, alloc: Alloc<'a>) -> Self {
+        Self::new_at_no_alloc(n, r, 0, alloc)
+    }
+
+    /// Creates a permuter positioned at the permutation with the given `index` in the traversal
+    /// order, such that an interrupted traversal may be resumed from its [`position`](Self::position).
+    #[inline]
+    pub fn new_at(n: usize, r: usize, index: usize) -> Self {
+        Self::new_at_no_alloc(n, r, index, Alloc::new(r))
+    }
+
+    #[inline]
+    pub fn new_at_no_alloc(n: usize, r: usize, index: usize, alloc: Alloc<'a>) -> Self {
+        let Alloc {
+            combiner_ordinals,
+            whole_ordinals_stack,
+            mut omitted,
+            ordinals,
+        } = alloc;
+        let expected_stack_len = Alloc::stack_len(r);
+        debug_assert_eq!(combiner_ordinals.len(), r, "combiner ordinals length must equal r");
+        debug_assert_eq!(
+            whole_ordinals_stack.len(),
+            expected_stack_len,
+            "whole ordinals stack length must equal r(r+1)/2"
+        );
+        debug_assert_eq!(omitted.len(), r, "omitted length must equal r");
+        debug_assert_eq!(ordinals.len(), r, "ordinals length must equal r");
+
+        let count = checked_count_permutations(n, r);
+        assert!(
+            count.is_none_or(|count| index < count),
+            "index {index} is out of bounds for {n}P{r}"
+        );
+        // an index is split into mixed-radix digits; a radix that overflows usize exceeds any
+        // index, so its digit is zero
+        let split = |index: usize, radix: Option<usize>| match radix {
+            Some(radix) => (index / radix, index % radix),
+            None => (0, index),
+        };
+        let (combination, mut arrangement) = split(index, checked_count_permutations(r, r));
+
+        for (depth, omitted) in omitted.iter_mut().enumerate() {
+            let frame_len = r - depth;
+            let (digit, remainder) = split(arrangement, checked_count_permutations(frame_len - 1, frame_len - 1));
+            arrangement = remainder;
+            *omitted = frame_len - 1 - digit;
         }
 
-        if !_permute_no_alloc(
-            &mut whole_ordinals_stack,
-            &mut split_ordinals_stack,
-            0,
+        let mut permuter = Self {
             r,
-            &mut ordinals,
-            &mut f,
-            0,
-        ) {
-            break;
-        }
-        if !combiner.advance() {
-            break;
+            combiner: Combiner::new_at_no_alloc(n, combination, combiner_ordinals),
+            frames: whole_ordinals_stack,
+            omitted,
+            ordinals,
+            position: index,
+            count,
+        };
+        permuter.load_combination();
+        permuter
+    }
+
+    /// The index of the current permutation in the traversal order.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of permutations yet to be read, counting the current one, or `None` if the
+    /// total number of permutations cannot be represented in a `usize`.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.count.map(|count| count - self.position)
+    }
+
+    /// Loads the current combination into the outermost frame, rebuilding all frames.
+    #[inline(always)]
+    fn load_combination(&mut self) {
+        let r = self.r;
+        self.frames[..r].copy_from_slice(self.combiner.read());
+        self.descend(0);
+    }
+
+    /// Rebuilds the frames deeper than `depth` and the ordinals placed from `depth` onwards,
+    /// following the omitted indices.
+    #[inline(always)]
+    fn descend(&mut self, depth: usize) {
+        let r = self.r;
+        let mut frame_start = frame_start(r, depth);
+        for depth in depth..r {
+            let frame_len = r - depth;
+            let omitted = self.omitted[depth];
+            self.ordinals[frame_len - 1] = self.frames[frame_start + omitted];
+            let child_start = frame_start + frame_len;
+            let mut child_index = child_start;
+            for index in 0..frame_len {
+                if index != omitted {
+                    self.frames[child_index] = self.frames[frame_start + index];
+                    child_index += 1;
+                }
+            }
+            frame_start = child_start;
         }
     }
 }
 
-#[inline]
-fn _permute_no_alloc(
-    whole_ordinals_stack: &mut [usize],
-    split_ordinals_stack: &mut [usize],
-    stack_start: usize,
-    stack_len: usize,
-    ordinals: &mut [usize],
-    f: &mut impl FnMut(&[usize]) -> bool,
-    depth: usize,
-) -> bool {
-    if stack_len != 0 {
-        // SAFETY: by the nonoverlapping index ranges stack_start..stack_start+stack_len
-        // and child_stack_start..child_stack_start+child_stack_len.
-        let whole_ordinals_stack_shadow: *const _ = whole_ordinals_stack;
-        let whole_ordinals_stack_shadow = unsafe { &*whole_ordinals_stack_shadow };
-        let split_ordinals_stack_shadow: *mut _ = split_ordinals_stack;
-        let split_ordinals_stack_shadow = unsafe { &mut *split_ordinals_stack_shadow };
-
-        let mut splitter = SplitCombiner::new_no_alloc(CaptureMut::Borrowed(
-            &mut split_ordinals_stack_shadow[stack_start..stack_start + stack_len - 1],
-        ));
-
-        // demarcation pointers for the next (child) frame of within whole_ordinals_stack
-        let (child_stack_start, child_stack_len) = (stack_start + stack_len, stack_len - 1);
-        loop {
-            // the next child combination to recurse into
-            let Split(head, tail) = splitter.read();
-
-            // resolve the ordinals produced by the splitter into the actual ordinals in the context of the parent combination
-            for (index, head_ordinal) in head
-                .iter()
-                .map(|head_ordinal| whole_ordinals_stack_shadow[stack_start + *head_ordinal])
-                .enumerate()
-            {
-                whole_ordinals_stack[child_stack_start + index] = head_ordinal;
-            }
-            let tail_ordinal = whole_ordinals_stack[stack_start + tail];
-            // println!(
-            //     "{}{depth} — elements: {:?}, permuting split {:?}-{tail_ordinal}, ordinals: {:?}, {stack_start}|{stack_len}",
-            //     "  ".repeat(depth),
-            //     &whole_ordinals_stack[stack_start..stack_start + stack_len],
-            //     &whole_ordinals_stack[child_stack_start..child_stack_start + child_stack_len],
-            //     &ordinals[ordinals.len() - depth..]
-            // );
-            ordinals[ordinals.len() - depth - 1] = tail_ordinal;
-
-            if !_permute_no_alloc(
-                whole_ordinals_stack,
-                split_ordinals_stack,
-                child_stack_start,
-                child_stack_len,
-                ordinals,
-                f,
-                depth + 1,
-            ) {
-                return false;
-            }
+/// The offset of the frame at the given `depth` within the whole ordinals stack.
+#[inline(always)]
+fn frame_start(r: usize, depth: usize) -> usize {
+    Alloc::stack_len(r) - Alloc::stack_len(r - depth)
+}
+
+impl Generator for StickyPermuter<'_> {
+    type Item = [usize];
+
+    #[inline]
+    fn read(&self) -> &Self::Item {
+        &self.ordinals
+    }
 
-            if !splitter.advance() {
-                break;
+    #[inline]
+    fn advance(&mut self) -> bool {
+        match self.omitted.iter().rposition(|&omitted| omitted > 0) {
+            Some(depth) => {
+                self.omitted[depth] -= 1;
+                for (deeper, omitted) in self.omitted.iter_mut().enumerate().skip(depth + 1) {
+                    *omitted = self.r - deeper - 1;
+                }
+                self.descend(depth);
+            }
+            None => {
+                if !self.combiner.advance() {
+                    return false;
+                }
+                for (depth, omitted) in self.omitted.iter_mut().enumerate() {
+                    *omitted = self.r - depth - 1;
+                }
+                self.load_combination();
             }
         }
+        self.position += 1;
         true
-    } else {
-        //println!("{} completed ordinals: {ordinals:?}", "  ".repeat(depth));
-        f(ordinals)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
     }
 }
 
+#[inline]
+pub fn permute(n: usize, r: usize, f: impl FnMut(&[usize]) -> bool) {
+    permute_no_alloc(n, r, Alloc::new(r), f)
+}
+
+#[inline]
+pub fn permute_no_alloc(n: usize, r: usize, alloc: Alloc, mut f: impl FnMut(&[usize]) -> bool) {
+    let mut permuter = StickyPermuter::new_no_alloc(n, r, alloc);
+    while f(permuter.read()) && permuter.advance() {}
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::comb::sticky_permuter::permute;
-    use crate::comb::tests::inner_array_to_vec;
+    use crate::comb::count_permutations;
+    use crate::comb::sticky_permuter::{permute, StickyPermuter};
+    use crate::comb::tests::{inner_array_to_vec, iterate_generator};
+    use crate::stream::generator::Generator;
+    use crate::stream::itemiser::Itemiser;
 
     fn iterate_sticky(n: usize, r: usize) -> Vec<Vec<usize>> {
         let mut outputs = vec![];
@@ -330,4 +402,49 @@ mod tests {
         let expected_outputs = vec![[0, 1], [1, 0], [0, 2], [2, 0], [0, 3], [3, 0]];
         assert_eq!(inner_array_to_vec(expected_outputs), outputs);
     }
+
+    #[test]
+    fn generator_matches_permute() {
+        for (n, r) in [(0, 0), (4, 0), (4, 2), (4, 4), (6, 3)] {
+            let permuter = StickyPermuter::new(n, r);
+            let count = count_permutations(n, r);
+            assert_eq!((count, Some(count)), permuter.size_hint());
+            assert_eq!(iterate_sticky(n, r), iterate_generator(permuter));
+        }
+    }
+
+    #[test]
+    fn resume_at_position() {
+        let outputs = iterate_sticky(5, 3);
+        for index in [0, 1, 5, 6, 17, 59] {
+            let permuter = StickyPermuter::new_at(5, 3, index);
+            assert_eq!(index, permuter.position());
+            assert_eq!(&outputs[index..], iterate_generator(permuter));
+        }
+    }
+
+    #[test]
+    fn composes_with_itemiser() {
+        let outputs = StickyPermuter::new(4, 2)
+            .into_itemiser()
+            .filter(|ordinals| ordinals[0] > ordinals[1])
+            .into_vec();
+        let expected_outputs = vec![[1, 0], [2, 0], [3, 0], [2, 1], [3, 1], [3, 2]];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn exact_size() {
+        let mut permuter = StickyPermuter::new(4, 2);
+        for position in 0..12 {
+            assert_eq!(Some(12 - position), permuter.remaining());
+            assert_eq!((12 - position, Some(12 - position)), permuter.size_hint());
+            assert_eq!(position < 11, permuter.advance());
+        }
+
+        let permuter = StickyPermuter::new(30, 25);
+        assert_eq!(None, permuter.remaining());
+        assert_eq!((usize::MAX, None), permuter.size_hint());
+        assert_eq!((0..25).collect::<Vec<_>>(), permuter.read());
+    }
 }
@@ -0,0 +1,201 @@
+//! A streaming permuter of <sup>n</sup>P<sub>n</sub> permutations in Steinhaus–Johnson–Trotter
+//! (plain changes) order, such that each successive permutation differs from its predecessor by
+//! a swap of two adjacent ordinals.
+//!
+//! For example, in a <sup>3</sup>P<sub>3</sub> traversal, the initial permutation `[0, 1, 2]`
+//! is succeeded by `[0, 2, 1]`, `[2, 0, 1]`, `[2, 1, 0]`, `[1, 2, 0]` and `[1, 0, 2]`.
+//!
+//! The implementation follows Algorithm P of Knuth, <i>The Art of Computer Programming</i>,
+//! Volume 4A, §7.2.1.2.
+
+use crate::capture::CaptureMut;
+use crate::comb::bitmap::Bitmap;
+use crate::comb::{checked_count_permutations, exact_size_hint};
+use crate::stream::generator::Generator;
+
+#[derive(Debug)]
+pub struct Alloc<'a> {
+    pub ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    pub inversions: CaptureMut<'a, Vec<usize>, [usize]>,
+    pub descending: CaptureMut<'a, Bitmap>,
+}
+
+impl Alloc<'_> {
+    #[inline]
+    pub fn new(n: usize) -> Self {
+        Self {
+            ordinals: vec![0; n].into(),
+            inversions: vec![0; n].into(),
+            descending: Bitmap::empty(n).into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TrotterPermuter<'a> {
+    ordinals: CaptureMut<'a, Vec<usize>, [usize]>,
+    inversions: CaptureMut<'a, Vec<usize>, [usize]>,
+    descending: CaptureMut<'a, Bitmap>,
+    changed: [usize; 2],
+    changes: usize,
+    position: usize,
+    count: Option<usize>,
+}
+
+impl<'a> TrotterPermuter<'a> {
+    #[inline]
+    pub fn new(n: usize) -> Self {
+        Self::new_no_alloc(Alloc::new(n))
+    }
+
+    #[inline]
+    pub fn new_no_alloc(alloc: Alloc<'a>) -> Self {
+        let Alloc { mut ordinals, mut inversions, mut descending } = alloc;
+        let n = ordinals.len();
+        debug_assert_eq!(n, inversions.len(), "length of inversions must equal n");
+        debug_assert_eq!(n, descending.len(), "length of the bitmap must equal n");
+        for (index, ordinal) in ordinals.iter_mut().enumerate() {
+            *ordinal = index;
+        }
+        inversions.fill(0);
+        descending.clear_all();
+        let count = checked_count_permutations(n, n);
+        Self {
+            ordinals,
+            inversions,
+            descending,
+            changed: [0; 2],
+            changes: 0,
+            position: 0,
+            count,
+        }
+    }
+
+    /// The positions of the ordinals swapped by the last advance, in ascending order; empty prior
+    /// to the first.
+    #[inline]
+    pub fn changed(&self) -> &[usize] {
+        &self.changed[..self.changes]
+    }
+
+    /// The number of permutations advanced over so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of permutations yet to be read, counting the current one, or `None` if the
+    /// total number of permutations cannot be represented in a `usize`.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.count.map(|count| count - self.position)
+    }
+}
+
+impl Generator for TrotterPermuter<'_> {
+    type Item = [usize];
+
+    #[inline]
+    fn read(&self) -> &Self::Item {
+        &self.ordinals
+    }
+
+    #[inline]
+    fn advance(&mut self) -> bool {
+        // the largest ordinal that can move in its direction moves by one place; every larger
+        // ordinal has reached an end of its range, and reverses direction, while offsetting the
+        // positions of the smaller ordinals by the number of larger ordinals resting to their left
+        let mut offset = 0;
+        for ordinal in (1..self.ordinals.len()).rev() {
+            let inversions = self.inversions[ordinal];
+            if self.descending.get(ordinal) {
+                if inversions > 0 {
+                    return self.swap(ordinal, inversions, inversions - 1, offset);
+                }
+                self.descending.clear(ordinal);
+            } else {
+                if inversions < ordinal {
+                    return self.swap(ordinal, inversions, inversions + 1, offset);
+                }
+                self.descending.set(ordinal);
+                offset += 1;
+            }
+        }
+        false
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
+    }
+}
+
+impl TrotterPermuter<'_> {
+    /// Moves the `ordinal` from the position implied by its `inversions` to that implied by
+    /// `next_inversions`.
+    #[inline(always)]
+    fn swap(&mut self, ordinal: usize, inversions: usize, next_inversions: usize, offset: usize) -> bool {
+        let from = ordinal - inversions + offset;
+        let to = ordinal - next_inversions + offset;
+        self.ordinals.swap(from, to);
+        self.inversions[ordinal] = next_inversions;
+        self.changed = [from.min(to), from.max(to)];
+        self.changes = 2;
+        self.position += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comb::count_permutations;
+    use crate::comb::permuter::Permuter;
+    use crate::comb::tests::{inner_array_to_vec, iterate_generator};
+
+    #[test]
+    fn trotter_3p3() {
+        let outputs = iterate_generator(TrotterPermuter::new(3));
+        let expected_outputs = vec![
+            [0, 1, 2],
+            [0, 2, 1],
+            [2, 0, 1],
+            [2, 1, 0],
+            [1, 2, 0],
+            [1, 0, 2],
+        ];
+        assert_eq!(inner_array_to_vec(expected_outputs), outputs);
+    }
+
+    #[test]
+    fn trotter_degenerate() {
+        assert_eq!(vec![Vec::<usize>::new()], iterate_generator(TrotterPermuter::new(0)));
+        assert_eq!(inner_array_to_vec(vec![[0]]), iterate_generator(TrotterPermuter::new(1)));
+        assert_eq!(inner_array_to_vec(vec![[0, 1], [1, 0]]), iterate_generator(TrotterPermuter::new(2)));
+    }
+
+    #[test]
+    fn minimal_change() {
+        for n in 2..=6 {
+            let count = count_permutations(n, n);
+            let mut permuter = TrotterPermuter::new(n);
+            assert_eq!((count, Some(count)), permuter.size_hint());
+            assert!(permuter.changed().is_empty());
+            let mut outputs = vec![permuter.read().to_vec()];
+            while permuter.advance() {
+                let previous = outputs.last().unwrap();
+                let current = permuter.read();
+                let differing = (0..n)
+                    .filter(|&index| previous[index] != current[index])
+                    .collect::<Vec<_>>();
+                assert_eq!(differing, permuter.changed());
+                assert_eq!(differing[0] + 1, differing[1]);
+                assert_eq!(Some(count - outputs.len()), permuter.remaining());
+                outputs.push(current.to_vec());
+            }
+            assert_eq!(count, outputs.len());
+            outputs.sort();
+            assert_eq!(iterate_generator(Permuter::new(n, n)), outputs);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Coupled entries — runners bracketed together as a single betting interest.
+//!
+//! In some jurisdictions, entries from the same stable (or the mutuel field) are coupled, such
+//! that a bet on the interest is won if any of its members finishes as required. A [`Coupling`]
+//! partitions the runners into interests, and aggregates the per-runner probabilities
+//! accordingly:
+//!
+//! * The win probability of an interest is the sum of its members' win probabilities.
+//! * An interest places if its best-finishing member places. The latter is the leader of the
+//!   interest, as computed by [`matchup::leaders`].
+//! * An exotic over interests is won by any podium that assigns a distinct member of the
+//!   stipulated interest to each place, i.e., a [`Ticket`] with one leg per place.
+
+use crate::comb::permuter::Permuter;
+use crate::exotics::ticket::Ticket;
+use crate::exotics::Exotic;
+use crate::matchup;
+use crate::matrix::Matrix;
+use crate::stream::generator::Generator;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coupling {
+    interests: Vec<Vec<usize>>,
+    interest_of: Vec<usize>,
+}
+
+impl Coupling {
+    /// Couples the runners in each of the `groups`, leaving every other runner as an interest of
+    /// its own. Interests are ordered by their lowest-numbered member.
+    pub fn new(runners: usize, groups: &[Vec<usize>]) -> Self {
+        const UNASSIGNED: usize = usize::MAX;
+        let mut interest_of = vec![UNASSIGNED; runners];
+        for (group_index, group) in groups.iter().enumerate() {
+            assert!(!group.is_empty(), "group {group_index} cannot be empty");
+            for &runner in group {
+                assert!(runner < runners, "runner {runner} is out of bounds for {runners} runners");
+                assert_eq!(
+                    UNASSIGNED, interest_of[runner],
+                    "runner {runner} cannot appear in more than one group"
+                );
+                interest_of[runner] = group_index;
+            }
+        }
+
+        let mut interests: Vec<Vec<usize>> = vec![];
+        let mut group_interests = vec![UNASSIGNED; groups.len()];
+        for (runner, interest) in interest_of.iter_mut().enumerate() {
+            match *interest {
+                UNASSIGNED => {
+                    *interest = interests.len();
+                    interests.push(vec![runner]);
+                }
+                group_index => {
+                    if group_interests[group_index] == UNASSIGNED {
+                        group_interests[group_index] = interests.len();
+                        interests.push(vec![]);
+                    }
+                    *interest = group_interests[group_index];
+                    interests[*interest].push(runner);
+                }
+            }
+        }
+        Self { interests, interest_of }
+    }
+
+    /// The member runners of each interest.
+    #[inline]
+    pub fn interests(&self) -> &[Vec<usize>] {
+        &self.interests
+    }
+
+    /// The interest to which the given `runner` belongs.
+    #[inline]
+    pub fn interest_of(&self, runner: usize) -> usize {
+        self.interest_of[runner]
+    }
+
+    /// Win probability of each interest.
+    pub fn win_probs(&self, win_probs: &[f64]) -> Vec<f64> {
+        self.validate_runners(win_probs.len());
+        self.interests
+            .iter()
+            .map(|members| members.iter().map(|&runner| win_probs[runner]).sum())
+            .collect()
+    }
+
+    /// Aggregates a runner `summary` into an interest summary, in which each cell is the
+    /// probability of some member of the interest (column) finishing in the rank (row).
+    pub fn aggregate(&self, summary: &Matrix<f64>) -> Matrix<f64> {
+        self.validate_runners(summary.cols());
+        let mut aggregate = Matrix::allocate(summary.rows(), self.interests.len());
+        for rank in 0..summary.rows() {
+            let rank_probs = summary.row_slice(rank);
+            for (interest, members) in self.interests.iter().enumerate() {
+                aggregate[(rank, interest)] = members.iter().map(|&runner| rank_probs[runner]).sum();
+            }
+        }
+        aggregate
+    }
+
+    /// Probability of each interest having a member finish in the first `places` ranks.
+    pub fn place_probs(&self, probs: &Matrix<f64>, places: usize) -> Vec<f64> {
+        self.validate_runners(probs.cols());
+        assert!(
+            places <= probs.rows(),
+            "places ({places}) cannot exceed the number of rows in the probabilities matrix ({})",
+            probs.rows()
+        );
+        self.interests
+            .iter()
+            .map(|members| {
+                let leaders = matchup::leaders(probs, members);
+                leaders.by_rank.flatten()[..places * members.len()].iter().sum()
+            })
+            .collect()
+    }
+
+    /// The ticket comprising every podium in which a distinct member of each of the `interests`
+    /// finishes in the corresponding place.
+    pub fn ticket(&self, interests: &[usize]) -> Ticket {
+        Ticket::new(
+            interests
+                .iter()
+                .map(|&interest| self.interests[interest].clone())
+                .collect(),
+        )
+    }
+
+    /// Probability that the selected `interests` finish as stipulated by the `exotic`.
+    pub fn exotic_prob(&self, probs: &Matrix<f64>, exotic: &Exotic, interests: &[usize]) -> f64 {
+        self.validate_runners(probs.cols());
+        debug_assert_eq!(
+            exotic.places(),
+            interests.len(),
+            "selection length must equal the number of places spanned by the exotic"
+        );
+        if exotic.is_ordered() {
+            return self.ticket(interests).summary(probs).prob;
+        }
+
+        // sum over the distinct orderings of the selected interests, which may repeat
+        let places = interests.len();
+        let mut orderings: Vec<Vec<usize>> = vec![];
+        let mut permuter = Permuter::new(places, places);
+        loop {
+            let ordering = permuter
+                .read()
+                .iter()
+                .map(|&ordinal| interests[ordinal])
+                .collect::<Vec<_>>();
+            if !orderings.contains(&ordering) {
+                orderings.push(ordering);
+            }
+            if !permuter.advance() {
+                break;
+            }
+        }
+        orderings
+            .iter()
+            .map(|ordering| self.ticket(ordering).summary(probs).prob)
+            .sum()
+    }
+
+    #[inline]
+    fn validate_runners(&self, runners: usize) {
+        assert_eq!(
+            self.interest_of.len(),
+            runners,
+            "number of runners must equal the number of runners in the coupling"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harville::{classic, harville};
+    use crate::probs::SliceExt;
+    use crate::testing::{assert_slice_f64_relative, dilated_probs_with};
+    use assert_float_eq::*;
+
+    const WIN_PROBS: [f64; 5] = [0.3, 0.25, 0.2, 0.15, 0.1];
+    const DILATIVES: [f64; 5] = [0.0, 0.1, 0.2, 0.3, 0.4];
+
+    #[test]
+    fn interests() {
+        let coupling = Coupling::new(5, &[vec![3, 1]]);
+        assert_eq!(&[vec![0], vec![1, 3], vec![2], vec![4]], coupling.interests());
+        assert_eq!(1, coupling.interest_of(3));
+        assert_eq!(3, coupling.interest_of(4));
+        assert_slice_f64_relative(&[0.3, 0.4, 0.2, 0.1], &coupling.win_probs(&WIN_PROBS), 1e-12);
+    }
+
+    #[test]
+    fn aggregate_summary() {
+        let coupling = Coupling::new(5, &[vec![1, 3]]);
+        let summary = classic::summary(&dilated_probs_with(&WIN_PROBS, &DILATIVES[..3]));
+        let aggregate = coupling.aggregate(&summary);
+        for rank in 0..3 {
+            assert_float_relative_eq!(1.0, aggregate.row_slice(rank).sum());
+            assert_float_relative_eq!(summary[(rank, 1)] + summary[(rank, 3)], aggregate[(rank, 1)]);
+        }
+    }
+
+    #[test]
+    fn place_probs() {
+        let coupling = Coupling::new(5, &[vec![0, 4]]);
+        let probs = dilated_probs_with(&WIN_PROBS, &DILATIVES[..5]);
+        let place_probs = coupling.place_probs(&probs, 2);
+
+        // uncoupled interests place as the runners themselves
+        let summary = classic::summary(&probs);
+        for (interest, members) in coupling.interests().iter().enumerate().skip(1) {
+            let runner = members[0];
+            assert_float_relative_eq!(summary[(0, runner)] + summary[(1, runner)], place_probs[interest]);
+        }
+
+        // the coupled interest places unless neither member places
+        let mut miss_prob = 0.0;
+        let others = [1, 2, 3];
+        for &first in &others {
+            for &second in &others {
+                if first != second {
+                    miss_prob += harville(&probs, &[first, second]);
+                }
+            }
+        }
+        assert_float_relative_eq!(1.0 - miss_prob, place_probs[0]);
+    }
+
+    #[test]
+    fn exotics_over_interests() {
+        let coupling = Coupling::new(5, &[vec![0, 1]]);
+        let probs = dilated_probs_with(&WIN_PROBS, &DILATIVES[..2]);
+        assert_float_relative_eq!(
+            harville(&probs, &[0, 2]) + harville(&probs, &[1, 2]),
+            coupling.exotic_prob(&probs, &Exotic::Exacta, &[0, 1])
+        );
+        assert_float_relative_eq!(
+            harville(&probs, &[0, 1]) + harville(&probs, &[1, 0]),
+            coupling.exotic_prob(&probs, &Exotic::Quinella, &[0, 0])
+        );
+        assert_float_relative_eq!(
+            harville(&probs, &[0, 2])
+                + harville(&probs, &[1, 2])
+                + harville(&probs, &[2, 0])
+                + harville(&probs, &[2, 1]),
+            coupling.exotic_prob(&probs, &Exotic::Quinella, &[1, 0])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "runner 1 cannot appear in more than one group")]
+    fn overlapping_groups() {
+        Coupling::new(4, &[vec![0, 1], vec![1, 2]]);
+    }
+}
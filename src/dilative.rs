@@ -24,15 +24,22 @@ impl<'a> DilatedProbs<'a> {
     pub fn with_podium_places(self, podium_places: usize) -> Self {
         self.with_dilatives(Capture::Owned(vec![0.0; podium_places]))
     }
+
+    /// Populates the given `matrix` with the dilated probabilities, reshaping it as necessary.
+    /// Unlike conversion with [`From`], the storage of the matrix is reused.
+    pub fn populate(&self, matrix: &mut Matrix<f64>) {
+        let win_probs = self.win_probs.as_ref().expect("no win probabilities specified");
+        let dilatives = self.dilatives.as_ref().expect("no dilatives specified");
+        matrix.reshape(dilatives.len(), win_probs.len());
+        matrix.clone_row(win_probs);
+        dilatives.dilate_rows_power(matrix);
+    }
 }
 
 impl From<DilatedProbs<'_>> for Matrix<f64> {
     fn from(probs: DilatedProbs) -> Self {
-        let win_probs = probs.win_probs.expect("no win probabilities specified");
-        let dilatives = probs.dilatives.expect("no dilatives specified");
-        let mut matrix = Matrix::allocate(dilatives.len(), win_probs.len());
-        matrix.clone_row(&win_probs);
-        dilatives.dilate_rows_power(&mut matrix);
+        let mut matrix = Matrix::empty();
+        probs.populate(&mut matrix);
         matrix
     }
 }
\ No newline at end of file
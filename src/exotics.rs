@@ -0,0 +1,258 @@
+//! Pricing of exotic bets — exactas, quinellas, trifectas, superfectas and first-fours — using
+//! the Harville model over a matrix of (possibly dilated) per-rank probabilities.
+//!
+//! The probabilities matrix is typically produced from [`DilatedProbs`](crate::dilative::DilatedProbs)
+//! and must have at least as many rows as the number of places spanned by the exotic.
+
+pub mod ticket;
+
+use crate::capture::CaptureMut;
+use crate::comb::combiner::Combiner;
+use crate::comb::permuter;
+use crate::comb::permuter::Permuter;
+use crate::harville::harville;
+use crate::market::{Market, Overround, PriceBounds};
+use crate::matrix::Matrix;
+use crate::stream::generator::Generator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exotic {
+    /// First and second, in the exact order.
+    Exacta,
+
+    /// First and second, in any order.
+    Quinella,
+
+    /// First, second and third, in the exact order.
+    Trifecta,
+
+    /// First four, in the exact order.
+    Superfecta,
+
+    /// First four, in the exact order. (The same bet as a [`Exotic::Superfecta`], known by a different
+    /// name in some jurisdictions.)
+    FirstFour,
+}
+
+impl Exotic {
+    /// The number of places spanned by the exotic.
+    #[inline]
+    pub fn places(&self) -> usize {
+        match self {
+            Exotic::Exacta | Exotic::Quinella => 2,
+            Exotic::Trifecta => 3,
+            Exotic::Superfecta | Exotic::FirstFour => 4,
+        }
+    }
+
+    /// Whether the selection must be matched in the exact order.
+    #[inline]
+    pub fn is_ordered(&self) -> bool {
+        !matches!(self, Exotic::Quinella)
+    }
+}
+
+/// Probability that the runners in `selection` finish as stipulated by the `exotic`.
+pub fn prob(probs: &Matrix<f64>, exotic: &Exotic, selection: &[usize]) -> f64 {
+    debug_assert_eq!(
+        exotic.places(),
+        selection.len(),
+        "selection length must equal the number of places spanned by the exotic"
+    );
+    if exotic.is_ordered() {
+        ordered(probs, selection)
+    } else {
+        unordered(probs, selection)
+    }
+}
+
+/// Probability that the runners in `selection` occupy the first `selection.len()` places in the
+/// exact order.
+#[inline]
+pub fn ordered(probs: &Matrix<f64>, selection: &[usize]) -> f64 {
+    validate_probs(probs, selection.len());
+    harville(probs, selection)
+}
+
+/// Probability that the runners in `selection` occupy the first `selection.len()` places in any
+/// order.
+pub fn unordered(probs: &Matrix<f64>, selection: &[usize]) -> f64 {
+    validate_probs(probs, selection.len());
+    let places = selection.len();
+    let mut podium = vec![0; places];
+    let mut permuter = Permuter::new(places, places);
+    let mut prob = 0.0;
+    loop {
+        for (index, &ordinal) in permuter.read().iter().enumerate() {
+            podium[index] = selection[ordinal];
+        }
+        prob += harville(probs, &podium);
+        if !permuter.advance() {
+            break;
+        }
+    }
+    prob
+}
+
+/// A market comprising every possible selection of the given exotic, along with its probability
+/// and price.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExoticMarket {
+    pub selections: Vec<Vec<usize>>,
+    pub market: Market,
+}
+
+impl ExoticMarket {
+    /// The price of the given `selection`, or `None` if the selection is not in the market.
+    pub fn price(&self, selection: &[usize]) -> Option<f64> {
+        self.selections
+            .iter()
+            .position(|candidate| candidate == selection)
+            .map(|index| self.market.prices[index])
+    }
+}
+
+/// Enumerates all selections of the given `exotic` over the runners in `probs`, framing a market
+/// with the desired `overround`.
+///
+/// Ordered exotics enumerate every permutation of the runners over the spanned places;
+/// unordered exotics enumerate every combination.
+pub fn frame(
+    probs: &Matrix<f64>,
+    exotic: &Exotic,
+    overround: &Overround,
+    bounds: &PriceBounds,
+) -> ExoticMarket {
+    let runners = probs.cols();
+    let places = exotic.places();
+    validate_probs(probs, places);
+
+    let mut selections = vec![];
+    let mut selection_probs = vec![];
+    if exotic.is_ordered() {
+        let mut permuter = Permuter::new_no_alloc(places, permuter::Alloc::new(runners, places));
+        loop {
+            let selection = permuter.read();
+            selection_probs.push(harville(probs, selection));
+            selections.push(selection.to_vec());
+            if !permuter.advance() {
+                break;
+            }
+        }
+    } else {
+        let mut combiner = Combiner::new_no_alloc(runners, CaptureMut::Owned(vec![0; places]));
+        loop {
+            let selection = combiner.read();
+            selection_probs.push(unordered(probs, selection));
+            selections.push(selection.to_vec());
+            if !combiner.advance() {
+                break;
+            }
+        }
+    }
+
+    ExoticMarket {
+        selections,
+        market: Market::frame(overround, selection_probs, bounds),
+    }
+}
+
+#[inline]
+fn validate_probs(probs: &Matrix<f64>, places: usize) {
+    debug_assert!(
+        probs.rows() >= places,
+        "number of rows in the probabilities matrix ({}) must be at least the number of places ({places})",
+        probs.rows()
+    );
+    debug_assert!(
+        probs.cols() >= places,
+        "number of columns in the probabilities matrix ({}) must be at least the number of places ({places})",
+        probs.cols()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probs::SliceExt;
+    use crate::testing::dilated_probs;
+    use assert_float_eq::*;
+
+    const BOUNDS: PriceBounds = 1.04..=10_001.0;
+
+    #[test]
+    fn places() {
+        assert_eq!(2, Exotic::Exacta.places());
+        assert_eq!(2, Exotic::Quinella.places());
+        assert_eq!(3, Exotic::Trifecta.places());
+        assert_eq!(4, Exotic::Superfecta.places());
+        assert_eq!(4, Exotic::FirstFour.places());
+        assert!(!Exotic::Quinella.is_ordered());
+        assert!(Exotic::FirstFour.is_ordered());
+    }
+
+    #[test]
+    fn exacta() {
+        let probs = dilated_probs(&[0.6, 0.3, 0.1], 2);
+        assert_float_relative_eq!(0.6 * 0.3 / 0.4, prob(&probs, &Exotic::Exacta, &[0, 1]));
+        assert_float_relative_eq!(0.3 * 0.6 / 0.7, prob(&probs, &Exotic::Exacta, &[1, 0]));
+    }
+
+    #[test]
+    fn quinella() {
+        let probs = dilated_probs(&[0.6, 0.3, 0.1], 2);
+        let exacta_sum = prob(&probs, &Exotic::Exacta, &[0, 1]) + prob(&probs, &Exotic::Exacta, &[1, 0]);
+        assert_float_relative_eq!(exacta_sum, prob(&probs, &Exotic::Quinella, &[0, 1]));
+        assert_float_relative_eq!(exacta_sum, prob(&probs, &Exotic::Quinella, &[1, 0]));
+    }
+
+    #[test]
+    fn trifecta() {
+        let probs = dilated_probs(&[0.6, 0.3, 0.1], 3);
+        assert_float_relative_eq!(0.45, prob(&probs, &Exotic::Trifecta, &[0, 1, 2]));
+    }
+
+    #[test]
+    fn superfecta_same_as_first_four() {
+        let probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1, 0.0], 4);
+        assert_eq!(
+            prob(&probs, &Exotic::Superfecta, &[3, 1, 2, 0]),
+            prob(&probs, &Exotic::FirstFour, &[3, 1, 2, 0])
+        );
+    }
+
+    #[test]
+    fn frame_fair_exacta() {
+        let probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1], 2);
+        let market = frame(&probs, &Exotic::Exacta, &Overround::fair(), &BOUNDS);
+        assert_eq!(12, market.selections.len());
+        assert_float_relative_eq!(1.0, market.market.probs.sum());
+        assert_float_relative_eq!(1.0, market.market.offered_booksum());
+        let price = market.price(&[0, 1]).unwrap();
+        assert_float_relative_eq!(1.0 / (0.4 * 0.3 / 0.6), price);
+        assert_eq!(None, market.price(&[0, 0]));
+    }
+
+    #[test]
+    fn frame_quinella_with_overround() {
+        let probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1], 2);
+        let overround = Overround {
+            method: crate::market::OverroundMethod::Multiplicative,
+            value: 1.2,
+        };
+        let market = frame(&probs, &Exotic::Quinella, &overround, &BOUNDS);
+        assert_eq!(6, market.selections.len());
+        assert_float_relative_eq!(1.0, market.market.probs.sum());
+        assert_float_relative_eq!(1.2, market.market.offered_booksum());
+    }
+
+    #[test]
+    fn frame_trifecta_with_scratching() {
+        let probs = dilated_probs(&[0.6, 0.3, 0.1, 0.0], 3);
+        let market = frame(&probs, &Exotic::Trifecta, &Overround::fair(), &BOUNDS);
+        assert_eq!(24, market.selections.len());
+        assert_float_relative_eq!(1.0, market.market.probs.sum());
+        assert_eq!(f64::INFINITY, market.price(&[0, 1, 3]).unwrap());
+        market.market.validate();
+    }
+}
@@ -0,0 +1,200 @@
+//! Multi-line exotic tickets — boxes, bankers and standouts.
+//!
+//! A [`Ticket`] stipulates a set of admissible runners for each place (a _leg_). Every podium
+//! that assigns a distinct, admissible runner to each place is a _line_ on the ticket. For
+//! example, a boxed trifecta over runners `{0, 1, 2}` comprises 6 lines, while an exacta with
+//! runner `0` banked in first place and a field of `{1, 2, 3}` comprises 3 lines.
+//!
+//! Lines are traversed in lexicographic order.
+
+use crate::comb::bitmap::Bitmap;
+use crate::comb::constrained_permuter::ConstrainedPermuter;
+use crate::harville::harville;
+use crate::matrix::Matrix;
+use crate::stream::generator::Generator;
+use crate::stream::itemiser::Itemiser;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ticket {
+    legs: Vec<Vec<usize>>,
+}
+
+impl Ticket {
+    /// A ticket with explicitly specified legs, one for each place.
+    pub fn new(legs: Vec<Vec<usize>>) -> Self {
+        assert!(!legs.is_empty(), "a ticket must have at least one leg");
+        Self { legs }
+    }
+
+    /// A boxed ticket, admitting any of the given `runners` in each of the `places`.
+    pub fn boxed(runners: &[usize], places: usize) -> Self {
+        Self::new(vec![runners.to_vec(); places])
+    }
+
+    /// A ticket with the `bankers` fixed in the leading places, in that order, and any runner
+    /// from the `field` in each of the remaining places (up to `places`).
+    pub fn banked(bankers: &[usize], field: &[usize], places: usize) -> Self {
+        assert!(
+            bankers.len() <= places,
+            "number of bankers ({}) cannot exceed the number of places ({places})",
+            bankers.len()
+        );
+        let legs = bankers
+            .iter()
+            .map(|&banker| vec![banker])
+            .chain((bankers.len()..places).map(|_| field.to_vec()))
+            .collect();
+        Self::new(legs)
+    }
+
+    #[inline]
+    pub fn legs(&self) -> &[Vec<usize>] {
+        &self.legs
+    }
+
+    /// The number of places spanned by the ticket.
+    #[inline]
+    pub fn places(&self) -> usize {
+        self.legs.len()
+    }
+
+    /// Invokes `f` for each line on the ticket.
+    pub fn for_each_line(&self, f: impl FnMut(&[usize])) {
+        let runners = self
+            .legs
+            .iter()
+            .flatten()
+            .max()
+            .map_or(0, |&runner| runner + 1);
+        let allowed = self
+            .legs
+            .iter()
+            .map(|leg| Bitmap::from((leg.iter().copied(), runners)))
+            .collect::<Vec<_>>();
+        ConstrainedPermuter::new(runners, &allowed)
+            .into_itemiser()
+            .for_each(f);
+    }
+
+    /// All lines on the ticket.
+    pub fn lines(&self) -> Vec<Vec<usize>> {
+        let mut lines = vec![];
+        self.for_each_line(|line| lines.push(line.to_vec()));
+        lines
+    }
+
+    /// Counts the lines on the ticket, returning the total probability of a winning line
+    /// (one of the lines matching the podium).
+    pub fn summary(&self, probs: &Matrix<f64>) -> TicketSummary {
+        debug_assert!(
+            probs.rows() >= self.places(),
+            "number of rows in the probabilities matrix ({}) must be at least the number of places ({})",
+            probs.rows(),
+            self.places()
+        );
+        let mut summary = TicketSummary {
+            lines: 0,
+            prob: 0.0,
+        };
+        self.for_each_line(|line| {
+            summary.lines += 1;
+            summary.prob += harville(probs, line);
+        });
+        summary
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TicketSummary {
+    /// Number of distinct lines on the ticket.
+    pub lines: usize,
+
+    /// Probability that one of the lines wins.
+    pub prob: f64,
+}
+
+impl TicketSummary {
+    /// The fair price of the ticket taken as a whole, i.e., the reciprocal of the probability that
+    /// one of its lines wins.
+    #[inline]
+    pub fn fair_price(&self) -> f64 {
+        1.0 / self.prob
+    }
+
+    /// The fair price of a unit stake on each line, i.e., the dividend a winning line must
+    /// return per unit for the ticket to break even.
+    #[inline]
+    pub fn fair_price_per_line(&self) -> f64 {
+        self.lines as f64 / self.prob
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exotics::{ordered, unordered};
+    use crate::testing::dilated_probs;
+    use assert_float_eq::*;
+
+    #[test]
+    fn boxed_trifecta() {
+        let ticket = Ticket::boxed(&[0, 1, 2], 3);
+        assert_eq!(
+            vec![
+                vec![0, 1, 2],
+                vec![0, 2, 1],
+                vec![1, 0, 2],
+                vec![1, 2, 0],
+                vec![2, 0, 1],
+                vec![2, 1, 0],
+            ],
+            ticket.lines()
+        );
+
+        let probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1], 3);
+        let summary = ticket.summary(&probs);
+        assert_eq!(6, summary.lines);
+        assert_float_relative_eq!(unordered(&probs, &[0, 1, 2]), summary.prob);
+    }
+
+    #[test]
+    fn banked_exacta() {
+        let ticket = Ticket::banked(&[0], &[1, 2, 3], 2);
+        assert_eq!(vec![vec![0, 1], vec![0, 2], vec![0, 3]], ticket.lines());
+
+        let probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1], 2);
+        let summary = ticket.summary(&probs);
+        assert_eq!(3, summary.lines);
+        assert_float_relative_eq!(0.4, summary.prob);
+        assert_float_relative_eq!(2.5, summary.fair_price());
+        assert_float_relative_eq!(7.5, summary.fair_price_per_line());
+    }
+
+    #[test]
+    fn banked_with_banker_in_field() {
+        let ticket = Ticket::banked(&[0], &[0, 1, 2], 3);
+        assert_eq!(vec![vec![0, 1, 2], vec![0, 2, 1]], ticket.lines());
+    }
+
+    #[test]
+    fn standout_trifecta() {
+        let ticket = Ticket::new(vec![vec![0, 1], vec![0, 1, 2], vec![3]]);
+        let lines = ticket.lines();
+        assert_eq!(vec![vec![0, 1, 3], vec![0, 2, 3], vec![1, 0, 3], vec![1, 2, 3]], lines);
+
+        let probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1], 3);
+        let summary = ticket.summary(&probs);
+        assert_eq!(4, summary.lines);
+        let expected = lines.iter().map(|line| ordered(&probs, line)).sum::<f64>();
+        assert_float_relative_eq!(expected, summary.prob);
+    }
+
+    #[test]
+    fn no_lines() {
+        let ticket = Ticket::new(vec![vec![0], vec![0]]);
+        assert!(ticket.lines().is_empty());
+        let summary = ticket.summary(&dilated_probs(&[0.5, 0.5], 2));
+        assert_eq!(0, summary.lines);
+        assert_eq!(0.0, summary.prob);
+    }
+}
@@ -1,10 +1,16 @@
 use crate::matrix::Matrix;
 use crate::probs::SliceExt;
+use std::ops::Range;
 
+pub mod adaptive;
+pub mod balance;
 pub mod classic;
+pub mod conditional;
 pub mod mass_samp;
+pub mod monte_carlo;
 pub mod sticky_samp;
 pub mod rand_samp;
+pub mod subset_dp;
 
 #[inline]
 pub fn harville(probs: &Matrix<f64>, podium: &[usize]) -> f64 {
@@ -23,33 +29,119 @@ pub fn harville(probs: &Matrix<f64>, podium: &[usize]) -> f64 {
 }
 
 pub fn harville_est(probs: &[f64], rank_idx: usize, lambda: f64) -> Vec<f64> {
+    let mut rank_probs = vec![0.0; probs.len()];
+    harville_est_no_alloc(probs, rank_idx, lambda, &mut rank_probs);
+    rank_probs
+}
+
+/// Equivalent of [`harville_est`], writing the rank probabilities into `rank_probs`, whose
+/// length must equal that of `probs`.
+pub fn harville_est_no_alloc(probs: &[f64], rank_idx: usize, lambda: f64, rank_probs: &mut [f64]) {
+    debug_assert_eq!(
+        probs.len(),
+        rank_probs.len(),
+        "length of the rank probabilities must equal the number of win probabilities"
+    );
     let len_sub_1 = probs.len() as f64 - 1.0;
-    let mut rank_probs = probs
-        .iter()
-        .map(|win_prob| {
-            let r = ((1.0 - win_prob) / len_sub_1).powf(lambda);
-            let numer = r.powi(rank_idx as i32) * win_prob;
-            let denom = (2..=rank_idx + 1)
-                .map(|j| 1.0 - r.powi((j - 1) as i32))
-                .product::<f64>();
-            //println!("r={r}, numer={numer}, denom={denom}");
-            numer / denom
-        })
-        .collect::<Vec<_>>();
+    for (rank_prob, win_prob) in rank_probs.iter_mut().zip(probs) {
+        let r = ((1.0 - win_prob) / len_sub_1).powf(lambda);
+        let numer = r.powi(rank_idx as i32) * win_prob;
+        let denom = (2..=rank_idx + 1)
+            .map(|j| 1.0 - r.powi((j - 1) as i32))
+            .product::<f64>();
+        //println!("r={r}, numer={numer}, denom={denom}");
+        *rank_prob = numer / denom;
+    }
     rank_probs.normalise(1.0);
-    rank_probs
+}
+
+/// Withdraws the `runner` from a (dilated) probabilities matrix in place, without reallocating.
+///
+/// Under Harville, withdrawing a runner renormalises the probabilities of the remaining runners in
+/// each rank. Since dilation raises the win probabilities to a power before normalising, the
+/// result is the same as dilating the renormalised win probabilities. If the matrix spans the
+/// full field, its last rank is dropped along with the runner.
+pub fn scratch(probs: &mut Matrix<f64>, runner: usize) {
+    scratch_col(probs, runner);
+    for row in 0..probs.rows() {
+        probs.row_slice_mut(row).normalise(1.0);
+    }
+}
+
+/// Removes the `runner` column from a matrix with rows for ranks, dropping the last rank if the
+/// matrix would otherwise have more ranks than runners. Returns the number of remaining ranks.
+#[inline]
+pub(crate) fn scratch_col(matrix: &mut Matrix<f64>, runner: usize) -> usize {
+    matrix.remove_col(runner);
+    let ranks = matrix.rows().min(matrix.cols());
+    matrix.truncate_rows(ranks);
+    ranks
+}
+
+/// Assigns the win probabilities to the first row of a sampled `summary` and normalises the
+/// remaining rows.
+pub(crate) fn finalise_sampled(probs: &Matrix<f64>, summary: &mut Matrix<f64>) {
+    summary.row_slice_mut(0).copy_from_slice(probs.row_slice(0));
+    for row_idx in 1..summary.rows() {
+        summary.row_slice_mut(row_idx).normalise(1.0);
+    }
+}
+
+/// The number of podiums evaluated by a sampler that evaluates `quota(rank)` podiums for each
+/// runner in each rank from 1 onwards.
+///
+/// The sampled podiums are indexed rank by rank, then runner by runner, so that a contiguous range
+/// of indices may be sampled independently of the rest.
+pub(crate) fn count_sampled(runners: usize, ranks: usize, quota: impl Fn(usize) -> usize) -> usize {
+    (1..ranks).map(|rank| runners * quota(rank)).sum()
+}
+
+/// Splits the given `range` of sampled podium indices (as per [`count_sampled`]) into blocks
+/// sharing a rank and a runner, passing the rank, the runner and the indices of the podiums
+/// relative to the start of their block.
+pub(crate) fn for_each_sampled(
+    runners: usize,
+    ranks: usize,
+    quota: impl Fn(usize) -> usize,
+    range: Range<usize>,
+    mut f: impl FnMut(usize, usize, Range<usize>),
+) {
+    let mut block_start = 0;
+    for rank in 1..ranks {
+        let quota = quota(rank);
+        for runner in 0..runners {
+            let start = range.start.max(block_start);
+            let end = range.end.min(block_start + quota);
+            if start < end {
+                f(rank, runner, start - block_start..end - block_start);
+            }
+            block_start += quota;
+        }
+    }
+}
+
+/// Sums the `partials` cell-wise into `target`, in iteration order.
+pub(crate) fn reduce<'a>(partials: impl IntoIterator<Item = &'a Matrix<f64>>, target: &mut Matrix<f64>) {
+    target.fill(0.0);
+    for partial in partials {
+        debug_assert!(
+            partial.rows() == target.rows() && partial.cols() == target.cols(),
+            "partial summary must have the same dimensions as the target"
+        );
+        for (target, partial) in target.flatten_mut().iter_mut().zip(partial.flatten()) {
+            *target += partial;
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::stream::itemiser::Itemiser;
-    use crate::testing::assert_slice_f64_relative;
+    use crate::testing::{assert_slice_f64_relative, dilated_probs, dilated_probs_with};
     use assert_float_eq::assert_float_relative_eq;
 
-    use crate::capture::Capture;
     use crate::comb::enumerator::Enumerator;
     use crate::comb::is_unique_quadratic;
-    use crate::dilative::DilatedProbs;
 
     use super::*;
 
@@ -59,16 +151,202 @@ mod tests {
         prob: f64,
     }
 
+    #[test]
+    fn scratch_dilated() {
+        let mut probs = dilated_probs_with(&[0.4, 0.3, 0.2, 0.1], &[0.0, 0.2, 0.4, 0.6]);
+        scratch(&mut probs, 1);
+        let expected = dilated_probs_with(&[0.4 / 0.7, 0.2 / 0.7, 0.1 / 0.7], &[0.0, 0.2, 0.4]);
+        assert_eq!(3, probs.rows());
+        assert_slice_f64_relative(expected.flatten(), probs.flatten(), 1e-12);
+    }
+
+    #[test]
+    fn scratch_reuse_allocs() {
+        const WIN_PROBS: [f64; 6] = [0.3, 0.25, 0.2, 0.12, 0.08, 0.05];
+        fn assert_rescratched<F: FnMut(&Matrix<f64>) -> Matrix<f64>>(mut summary: F, runner: usize) {
+            let mut probs = dilated_probs_with(&WIN_PROBS, &[0.0, 0.1, 0.2]);
+            summary(&probs);
+            scratch(&mut probs, runner);
+            assert_slice_f64_relative(classic::summary(&probs).flatten(), summary(&probs).flatten(), 1e-9);
+        }
+
+        let mut alloc = classic::Alloc::new(6, 3);
+        assert_rescratched(
+            |probs| {
+                if alloc.summary.cols() > probs.cols() {
+                    alloc.scratch(2);
+                }
+                classic::summary_no_alloc(probs, &mut alloc);
+                (*alloc.summary).clone()
+            },
+            2,
+        );
+
+        let mut alloc = subset_dp::Alloc::new(6, 3);
+        assert_rescratched(
+            |probs| {
+                if alloc.summary.cols() > probs.cols() {
+                    alloc.scratch(0);
+                }
+                subset_dp::summary_no_alloc(probs, &mut alloc);
+                (*alloc.summary).clone()
+            },
+            0,
+        );
+
+        let mut alloc = mass_samp::Alloc::new(6, 3);
+        assert_rescratched(
+            |probs| {
+                if alloc.summary.cols() > probs.cols() {
+                    alloc.scratch(5);
+                }
+                mass_samp::summary_no_alloc(probs, 3, &mut alloc);
+                (*alloc.summary).clone()
+            },
+            5,
+        );
+
+        let mut alloc = sticky_samp::Alloc::new(6, 3);
+        assert_rescratched(
+            |probs| {
+                if alloc.summary.cols() > probs.cols() {
+                    alloc.scratch(4);
+                }
+                sticky_samp::summary_no_alloc(probs, 3, &mut alloc);
+                (*alloc.summary).clone()
+            },
+            4,
+        );
+
+        let mut alloc = rand_samp::Alloc::new(6, 3);
+        assert_rescratched(
+            |probs| {
+                if alloc.summary.cols() > probs.cols() {
+                    alloc.scratch(1);
+                }
+                rand_samp::summary_no_alloc(probs, 3, &mut alloc);
+                (*alloc.summary).clone()
+            },
+            1,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot scratch the only remaining runner")]
+    fn scratch_only_runner_alloc() {
+        let mut alloc = sticky_samp::Alloc::new(2, 2);
+        alloc.scratch(1);
+        alloc.scratch(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot scratch the only remaining runner")]
+    fn scratch_only_runner_classic_alloc() {
+        let mut alloc = classic::Alloc::new(2, 2);
+        alloc.scratch(1);
+        alloc.scratch(0);
+    }
+
+    #[test]
+    fn scratch_full_field_alloc() {
+        let mut probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1], 4);
+        let mut alloc = classic::Alloc::new(4, 4);
+        scratch(&mut probs, 3);
+        alloc.scratch(3);
+        classic::summary_no_alloc(&probs, &mut alloc);
+        assert_eq!(3, alloc.summary.rows());
+        assert_slice_f64_relative(classic::summary(&probs).flatten(), alloc.summary.flatten(), 1e-12);
+    }
+
+    #[test]
+    fn reduce_partials() {
+        let mut a = Matrix::allocate(1, 2);
+        a.clone_row(&[0.1, 0.2]);
+        let mut b = Matrix::allocate(1, 2);
+        b.clone_row(&[0.3, 0.4]);
+        let mut target = Matrix::allocate(1, 2);
+        target.fill(1.0);
+        reduce([&a, &b], &mut target);
+        assert_slice_f64_relative(&[0.4, 0.6], target.flatten(), 1e-12);
+    }
+
+    #[test]
+    fn sampled_blocks() {
+        let quota = |rank| [0, 2, 3][rank];
+        assert_eq!(10, count_sampled(2, 3, quota));
+        let mut blocks = vec![];
+        for_each_sampled(2, 3, quota, 1..8, |rank, runner, podiums| blocks.push((rank, runner, podiums)));
+        assert_eq!(vec![(1, 0, 1..2), (1, 1, 0..2), (2, 0, 0..3), (2, 1, 0..1)], blocks);
+    }
+
+    #[test]
+    fn sampled_summary_par() {
+        const WIN_PROBS: [f64; 7] = [0.3, 0.2, 0.15, 0.12, 0.1, 0.08, 0.05];
+        let probs = dilated_probs_with(&WIN_PROBS, &[0.0, 0.1, 0.2, 0.3]);
+        for threads in [1, 3, 8] {
+            // a runner's podiums may be split across threads, differing only in rounding
+            assert_slice_f64_relative(
+                mass_samp::summary(&probs, 2).flatten(),
+                mass_samp::summary_par(&probs, 2, threads).flatten(),
+                1e-12,
+            );
+            assert_slice_f64_relative(
+                sticky_samp::summary(&probs, 2).flatten(),
+                sticky_samp::summary_par(&probs, 2, threads).flatten(),
+                1e-12,
+            );
+
+            // with a sufficient degree, every permutation is evaluated
+            let exact = rand_samp::summary_par(&probs, 4, threads);
+            assert_slice_f64_relative(classic::summary(&probs).flatten(), exact.flatten(), 1e-12);
+
+            let sampled = rand_samp::summary_par(&probs, 2, threads);
+            assert_eq!(sampled, rand_samp::summary_par(&probs, 2, threads));
+            for row in sampled.into_iter() {
+                assert_float_relative_eq!(1.0, row.sum(), 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn sampled_summary_par_is_deterministic() {
+        const WIN_PROBS: [f64; 7] = [0.3, 0.2, 0.15, 0.12, 0.1, 0.08, 0.05];
+        let probs = dilated_probs_with(&WIN_PROBS, &[0.0, 0.1, 0.2, 0.3]);
+        let bits = |summary: Matrix<f64>| summary.flatten().iter().map(|cell| cell.to_bits()).collect::<Vec<_>>();
+        let expected_mass = bits(mass_samp::summary_par(&probs, 2, 3));
+        let expected_sticky = bits(sticky_samp::summary_par(&probs, 2, 3));
+        for _ in 0..10 {
+            assert_eq!(expected_mass, bits(mass_samp::summary_par(&probs, 2, 3)));
+            assert_eq!(expected_sticky, bits(sticky_samp::summary_par(&probs, 2, 3)));
+        }
+    }
+
+    #[test]
+    fn rand_samp_par_single_thread() {
+        let probs = dilated_probs(&[0.3, 0.2, 0.15, 0.12, 0.1, 0.08, 0.05], 4);
+        assert_eq!(rand_samp::summary(&probs, 2), rand_samp::summary_par(&probs, 2, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "number of threads must be greater than 0")]
+    fn summary_par_zero_threads() {
+        let probs = dilated_probs(&[0.5, 0.3, 0.2], 2);
+        mass_samp::summary_par(&probs, 1, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one allocation is required, one for each thread")]
+    fn summary_par_no_alloc_without_allocs() {
+        let probs = dilated_probs(&[0.5, 0.3, 0.2], 2);
+        classic::summary_par_no_alloc(&probs, &mut [], &mut Matrix::allocate(2, 3));
+    }
+
     #[test]
     fn harville_3x3_without_scratchings() {
         const WIN_PROBS: [f64; 3] = [0.6, 0.3, 0.1];
         const RANKS: usize = 3;
         const RUNNERS: usize = WIN_PROBS.len();
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_podium_places(3),
-        );
+        let probs = dilated_probs(&WIN_PROBS, 3);
         let enumerator = Enumerator::new(&[RUNNERS; RANKS]);
         let probs = enumerator
             .into_iter()
@@ -92,11 +370,7 @@ mod tests {
         const WIN_PROBS: [f64; 4] = [0.6, 0.3, 0.1, 0.0];
         const RANKS: usize = 3;
         const RUNNERS: usize = WIN_PROBS.len();
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_podium_places(RANKS),
-        );
+        let probs = dilated_probs(&WIN_PROBS, RANKS);
         let enumerator = Enumerator::new(&[RUNNERS; RANKS]);
         let probs = enumerator
             .into_iter()
@@ -125,11 +399,7 @@ mod tests {
         const WIN_PROBS: [f64; 4] = [0.4, 0.3, 0.2, 0.1];
         const RANKS: usize = 4;
         const RUNNERS: usize = WIN_PROBS.len();
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_podium_places(RANKS),
-        );
+        let probs = dilated_probs(&WIN_PROBS, RANKS);
         let enumerator = Enumerator::new(&[RUNNERS; RANKS]);
         let probs = enumerator
             .into_iter()
@@ -154,11 +424,7 @@ mod tests {
         const WIN_PROBS: [f64; 4] = [0.6, 0.3, 0.1, 0.0];
         const RANKS: usize = 1;
         const RUNNERS: usize = WIN_PROBS.len();
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_podium_places(RANKS),
-        );
+        let probs = dilated_probs(&WIN_PROBS, RANKS);
         let enumerator = Enumerator::new(&[RUNNERS; RANKS]);
         let probs = enumerator
             .into_iter()
@@ -182,11 +448,7 @@ mod tests {
         const WIN_PROBS: [f64; 4] = [0.6, 0.3, 0.1, 0.0];
         const RANKS: usize = 2;
         const RUNNERS: usize = WIN_PROBS.len();
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_podium_places(RANKS),
-        );
+        let probs = dilated_probs(&WIN_PROBS, RANKS);
         let enumerator = Enumerator::new(&[RUNNERS; RANKS]);
         let probs = enumerator
             .into_iter()
@@ -211,11 +473,7 @@ mod tests {
         const DILATIVES: [f64; 4] = [0.0, 0.1, 0.2, 0.3];
         const RANKS: usize = 4;
         const RUNNERS: usize = WIN_PROBS.len();
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_dilatives(Capture::Borrowed(&DILATIVES)),
-        );
+        let probs = dilated_probs_with(&WIN_PROBS, &DILATIVES);
         let enumerator = Enumerator::new(&[RUNNERS; RANKS]);
         let probs = enumerator
             .into_iter()
@@ -0,0 +1,163 @@
+//! Calibration of the sampling degree for the sampled Harville summaries.
+//!
+//! The samplers in [`mass_samp`], [`sticky_samp`] and [`rand_samp`] trade accuracy for speed
+//! through their `degree` parameter. [`calibrate_degree`] tries increasing degrees, from 1 up to
+//! the number of ranks, measuring each sampled summary against a caller-supplied reference
+//! summary, and settles on the smallest degree whose largest absolute cell error is within the
+//! tolerance. The reference is ordinarily the exact summary from [`subset_dp`] or
+//! [`classic`](super::classic), whose cost is exponential in the field size; it is left to the
+//! caller, so that it is paid once for each representative field rather than on every
+//! calibration. At a degree equal to the number of ranks, every sampler evaluates all permutations
+//! and so reproduces an exact reference; [`Calibration::within_tolerance`] reports whether the
+//! tolerance was met, which it may not be against an inexact reference or a zero tolerance.
+//!
+//! The calibrated degree depends chiefly on the field size and the number of ranks, and only
+//! weakly on the probabilities themselves. It is typically calibrated once for each
+//! (runners, ranks) pair on a representative field, cached, and reused for subsequent fields of
+//! the same shape.
+use crate::harville::{mass_samp, rand_samp, sticky_samp};
+use crate::matrix::Matrix;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sampler {
+    MassSamp,
+    StickySamp,
+    RandSamp,
+}
+
+impl Sampler {
+    /// Computes the sampled summary at the given `degree`.
+    pub fn summary(&self, probs: &Matrix<f64>, degree: usize) -> Matrix<f64> {
+        match self {
+            Sampler::MassSamp => mass_samp::summary(probs, degree),
+            Sampler::StickySamp => sticky_samp::summary(probs, degree),
+            Sampler::RandSamp => rand_samp::summary(probs, degree),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    /// The smallest degree meeting the tolerance or, failing that, the number of ranks.
+    pub degree: usize,
+
+    /// Largest absolute difference between a cell of the sampled summary and the reference
+    /// summary.
+    pub error: f64,
+
+    /// Whether `error` is within the tolerance. When `false`, no degree up to the number of ranks
+    /// met the tolerance.
+    pub within_tolerance: bool,
+
+    /// The summary sampled at the calibrated degree.
+    pub summary: Matrix<f64>,
+}
+
+/// Calibrates the `sampler` against the `reference` summary of `probs`, selecting the smallest
+/// degree that reproduces it to within `tolerance`, in terms of the largest absolute cell error.
+///
+/// The `reference` is trusted as given; it is normally the exact summary of `probs`. If no degree
+/// up to the number of ranks meets the tolerance, the returned calibration is for the number of
+/// ranks, with [`Calibration::within_tolerance`] cleared.
+pub fn calibrate_degree(
+    probs: &Matrix<f64>,
+    reference: &Matrix<f64>,
+    sampler: &Sampler,
+    tolerance: f64,
+) -> Calibration {
+    assert!(tolerance >= 0.0, "tolerance must be non-negative");
+    debug_assert!(
+        reference.rows() == probs.rows() && reference.cols() == probs.cols(),
+        "reference summary must have the same dimensions as the probabilities matrix"
+    );
+    let ranks = probs.rows();
+    let mut degree = 1;
+    loop {
+        let summary = sampler.summary(probs, degree);
+        let error = max_abs_error(reference, &summary);
+        let within_tolerance = error <= tolerance;
+        log::trace!("sampler: {sampler:?}, degree: {degree}, error: {error:.6}");
+        if within_tolerance || degree >= ranks {
+            return Calibration {
+                degree,
+                error,
+                within_tolerance,
+                summary,
+            };
+        }
+        degree += 1;
+    }
+}
+
+#[inline]
+fn max_abs_error(expected: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+    expected
+        .flatten()
+        .iter()
+        .zip(actual.flatten())
+        .map(|(expected, actual)| (expected - actual).abs())
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harville::subset_dp;
+    use crate::testing::dilated_probs_with;
+
+    const SAMPLERS: [Sampler; 3] = [Sampler::MassSamp, Sampler::StickySamp, Sampler::RandSamp];
+
+    const WIN_PROBS: [f64; 8] = [0.25, 0.2, 0.15, 0.12, 0.1, 0.08, 0.06, 0.04];
+    const DILATIVES: [f64; 4] = [0.0, 0.1, 0.2, 0.3];
+
+    #[test]
+    fn calibrate_degree_within_tolerance() {
+        let probs = dilated_probs_with(&WIN_PROBS, &DILATIVES);
+        let exact = subset_dp::summary(&probs);
+        for sampler in SAMPLERS {
+            let calibration = calibrate_degree(&probs, &exact, &sampler, 0.01);
+            println!("{sampler:?}: degree: {}, error: {}", calibration.degree, calibration.error);
+            assert!(calibration.within_tolerance);
+            assert!(calibration.error <= 0.01);
+            assert_eq!(max_abs_error(&exact, &calibration.summary), calibration.error);
+            if calibration.degree > 1 {
+                let lower = sampler.summary(&probs, calibration.degree - 1);
+                assert!(max_abs_error(&exact, &lower) > 0.01);
+            }
+        }
+    }
+
+    #[test]
+    fn calibrate_degree_loose_tolerance() {
+        let probs = dilated_probs_with(&WIN_PROBS, &DILATIVES);
+        let exact = subset_dp::summary(&probs);
+        for sampler in SAMPLERS {
+            assert_eq!(1, calibrate_degree(&probs, &exact, &sampler, 1.0).degree);
+        }
+    }
+
+    #[test]
+    fn calibrate_degree_exact() {
+        let probs = dilated_probs_with(&WIN_PROBS, &DILATIVES);
+        let exact = subset_dp::summary(&probs);
+        for sampler in SAMPLERS {
+            let calibration = calibrate_degree(&probs, &exact, &sampler, 1e-12);
+            assert!(calibration.degree <= probs.rows());
+            assert!(calibration.error <= 1e-12, "{sampler:?}: {}", calibration.error);
+            assert!(calibration.within_tolerance);
+        }
+    }
+
+    #[test]
+    fn calibrate_degree_unattainable() {
+        let probs = dilated_probs_with(&WIN_PROBS, &DILATIVES);
+        let mut reference = subset_dp::summary(&probs);
+        reference[(0, 0)] += 0.1;
+        for sampler in SAMPLERS {
+            let calibration = calibrate_degree(&probs, &reference, &sampler, 0.01);
+            assert_eq!(probs.rows(), calibration.degree);
+            assert!(calibration.error > 0.01);
+            assert!(!calibration.within_tolerance);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Balancing of sampled summaries by iterative proportional fitting (Sinkhorn–Knopp).
+//!
+//! The sampled summaries in [`mass_samp`](super::mass_samp) and
+//! [`sticky_samp`](super::sticky_samp) normalise each row, but their column sums — the place
+//! probabilities — drift, occasionally exceeding 1. Balancing alternately rescales the rows and
+//! the columns of ranks 1 onwards, keeping the first row fixed at the win probabilities:
+//!
+//! * Each row is scaled to sum to 1.
+//! * In a full-field summary (as many ranks as runners), the column of runner `j` is scaled to
+//!   sum to <code>1 - p<sub>j</sub></code>, where <code>p<sub>j</sub></code> is its win
+//!   probability, making the summary doubly stochastic.
+//! * In a truncated summary, the column sums of ranks 1 onwards are capped at
+//!   <code>1 - p<sub>j</sub></code>; only the columns exceeding their caps are scaled down.
+//!
+//! A row or column without any probability mass (as arises below the first row for a runner with
+//! a zero win probability) cannot be rescaled, and is left as is, without a target.
+//!
+//! The iteration ends once every column is within the tolerance of its target (or cap), or the
+//! iteration limit is reached. The rows are normalised last, so they always sum to 1.
+
+use crate::matrix::Matrix;
+use crate::probs::SliceExt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Largest acceptable deviation of a column sum from its target (or excess over its cap).
+    pub tolerance: f64,
+
+    /// Balancing stops after this many iterations, irrespective of the residual.
+    pub max_iterations: u64,
+}
+
+impl Config {
+    pub fn validate(&self) {
+        assert!(self.tolerance >= 0.0, "tolerance must be non-negative");
+        assert!(self.max_iterations > 0, "maximum number of iterations must be positive");
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tolerance: 1e-9,
+            max_iterations: 1_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub iterations: u64,
+
+    /// Largest deviation of a column sum from its target (or excess over its cap) at the last
+    /// iteration.
+    pub residual: f64,
+}
+
+impl Outcome {
+    #[inline]
+    pub fn converged(&self, config: &Config) -> bool {
+        self.residual <= config.tolerance
+    }
+}
+
+/// Balances the `summary` in place.
+pub fn balance(summary: &mut Matrix<f64>, config: &Config) -> Outcome {
+    let mut col_sums = vec![0.0; summary.cols()];
+    balance_no_alloc(summary, config, &mut col_sums)
+}
+
+/// Balances the `summary` in place, using `col_sums` as scratch space of length equal to the
+/// number of runners.
+pub fn balance_no_alloc(summary: &mut Matrix<f64>, config: &Config, col_sums: &mut [f64]) -> Outcome {
+    config.validate();
+    debug_assert_eq!(
+        summary.cols(),
+        col_sums.len(),
+        "number of columns in the summary matrix must equal to the column sums length"
+    );
+    debug_assert!(
+        summary.rows() <= summary.cols(),
+        "number of rows in the summary matrix cannot exceed the number of columns"
+    );
+    let ranks = summary.rows();
+    let full_field = ranks == summary.cols();
+    if ranks < 2 {
+        return Outcome {
+            iterations: 0,
+            residual: 0.0,
+        };
+    }
+
+    let mut iterations = 0;
+    loop {
+        for rank in 1..ranks {
+            let row = summary.row_slice_mut(rank);
+            if row.sum() > 0.0 {
+                row.normalise(1.0);
+            }
+        }
+        iterations += 1;
+
+        col_sums.fill(0.0);
+        for rank in 1..ranks {
+            for (col_sum, prob) in col_sums.iter_mut().zip(summary.row_slice(rank)) {
+                *col_sum += prob;
+            }
+        }
+        let mut residual: f64 = 0.0;
+        for (runner, col_sum) in col_sums.iter_mut().enumerate() {
+            if *col_sum <= 0.0 || col_sum.is_nan() {
+                // a column without mass has no target
+                *col_sum = 1.0;
+                continue;
+            }
+            let target = 1.0 - summary[(0, runner)];
+            let deviation = if full_field {
+                (*col_sum - target).abs()
+            } else {
+                (*col_sum - target).max(0.0)
+            };
+            residual = residual.max(deviation);
+
+            // repurpose the column sum as the column's scaling factor
+            *col_sum = if full_field || *col_sum > target {
+                target / *col_sum
+            } else {
+                1.0
+            };
+        }
+        log::trace!("iteration: {iterations}, residual: {residual:.9}");
+
+        if residual <= config.tolerance || iterations == config.max_iterations {
+            return Outcome { iterations, residual };
+        }
+
+        for rank in 1..ranks {
+            for (prob, factor) in summary.row_slice_mut(rank).iter_mut().zip(col_sums.iter()) {
+                *prob *= factor;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harville::{mass_samp, sticky_samp};
+    use crate::testing::dilated_probs;
+    use assert_float_eq::*;
+
+    const WIN_PROBS: [f64; 7] = [0.35, 0.2, 0.15, 0.12, 0.1, 0.05, 0.03];
+
+    #[test]
+    fn balance_full_field() {
+        let mut summary = mass_samp::summary(&dilated_probs(&WIN_PROBS, 7), 1);
+        let config = Config::default();
+        let outcome = balance(&mut summary, &config);
+        println!("outcome: {outcome:?}, summary:\n{}", summary.verbose());
+        assert!(outcome.converged(&config));
+        assert_eq!(&WIN_PROBS, summary.row_slice(0));
+        for row in summary.into_iter() {
+            assert_float_relative_eq!(1.0, row.sum(), 1e-9);
+        }
+        for col in 0..summary.cols() {
+            assert_float_relative_eq!(1.0, summary.col(col).sum::<f64>(), 1e-6);
+        }
+    }
+
+    #[test]
+    fn balance_truncated() {
+        let mut summary = sticky_samp::summary(&dilated_probs(&WIN_PROBS, 4), 1);
+        let config = Config::default();
+        let outcome = balance(&mut summary, &config);
+        assert!(outcome.converged(&config));
+        assert_eq!(&WIN_PROBS, summary.row_slice(0));
+        for row in summary.into_iter() {
+            assert_float_relative_eq!(1.0, row.sum(), 1e-9);
+        }
+        for col in 0..summary.cols() {
+            assert!(summary.col(col).sum::<f64>() <= 1.0 + 1e-6);
+        }
+    }
+
+    #[test]
+    fn balance_iteration_limit() {
+        let mut summary = mass_samp::summary(&dilated_probs(&WIN_PROBS, 7), 1);
+        let config = Config {
+            tolerance: 0.0,
+            max_iterations: 3,
+        };
+        let outcome = balance(&mut summary, &config);
+        assert_eq!(3, outcome.iterations);
+        for row in summary.into_iter() {
+            assert_float_relative_eq!(1.0, row.sum(), 1e-9);
+        }
+    }
+
+    #[test]
+    fn balance_zero_win_prob() {
+        // the runner with no chance of winning has no mass in its column, and the last rank none
+        // in its row
+        let mut summary = Matrix::allocate(3, 3);
+        summary.row_slice_mut(0).copy_from_slice(&[0.6, 0.4, 0.0]);
+        summary.row_slice_mut(1).copy_from_slice(&[0.5, 0.5, 0.0]);
+        let config = Config::default();
+        let outcome = balance(&mut summary, &config);
+        assert!(outcome.converged(&config), "{outcome:?}");
+        assert!(outcome.iterations < config.max_iterations);
+        assert_eq!(&[0.6, 0.4, 0.0], summary.row_slice(0));
+        assert_float_relative_eq!(0.4, summary[(1, 0)], 1e-9);
+        assert_float_relative_eq!(0.6, summary[(1, 1)], 1e-9);
+        assert_eq!(0.0, summary[(1, 2)]);
+        assert_eq!(&[0.0; 3], summary.row_slice(2));
+    }
+}
@@ -1,11 +1,35 @@
+//! Exact Harville summaries.
+//!
+//! [`summary_no_alloc`] traverses the podiums depth-first, carrying the partial product and the
+//! remaining probability mass of each rank down the tree, so that each additional rank costs
+//! O(1) rather than recomputing the [`harville`] product of the entire podium. The
+//! probability mass of each subtree is accumulated into the summary on the way back up.
+//!
+//! [`summary_par_no_alloc`] splits the runners in the first rank across threads, each
+//! traversing its share of the tree into its own [`Alloc`].
+//!
+//! [`summary_enumerated_no_alloc`] is the original traversal, which evaluates [`harville`]
+//! separately for every podium, enumerated either by state or by permutation index.
+//!
+//! [`summary_minimal_change_no_alloc`] visits the sets of podium runners in revolving-door order
+//! and the orderings of each set in plain changes order. Successive orderings differ by a swap of
+//! two adjacent ranks, so only the Harville factors of those two ranks are recomputed.
+
 use crate::capture::CaptureMut;
+use crate::comb::bitmap::Bitmap;
 use crate::comb::{count_permutations, is_unique_linear, pick_permutation, pick_state_hyper};
-use crate::harville::harville;
+use crate::comb::par::{self, fold_par_no_alloc, split_par_no_alloc, Permutations};
+use crate::comb::revolving_combiner::RevolvingCombiner;
+use crate::comb::trotter_permuter::{self, TrotterPermuter};
+use crate::stream::generator::Generator;
+use crate::harville::{harville, reduce, scratch_col};
 use crate::matrix::Matrix;
+use std::ops::Range;
 
 pub struct Alloc<'a> {
     pub podium: CaptureMut<'a, Vec<usize>, [usize]>,
-    pub bitmap:  CaptureMut<'a, Vec<bool>, [bool]>,
+    pub bitmap:  CaptureMut<'a, Bitmap>,
+    pub remaining: CaptureMut<'a, Matrix<f64>>,
     pub summary:  CaptureMut<'a, Matrix<f64>>,
 }
 
@@ -13,10 +37,24 @@ impl<'a> Alloc<'a> {
     pub fn new(runners: usize, ranks: usize) -> Self {
         Self {
             podium: vec![0; ranks].into(),
-            bitmap: vec![false; runners].into(),
+            bitmap: Bitmap::empty(runners).into(),
+            remaining: Matrix::allocate(ranks, ranks).into(),
             summary: Matrix::allocate(ranks, runners).into(),
         }
     }
+
+    /// Withdraws the `runner` from the allocation without reallocating, to suit a probabilities
+    /// matrix from which the runner has been [scratched](crate::harville::scratch).
+    pub fn scratch(&mut self, runner: usize) {
+        assert!(self.summary.cols() > 1, "cannot scratch the only remaining runner");
+        let ranks = scratch_col(&mut self.summary, runner);
+        self.bitmap.truncate(self.summary.cols());
+        self.podium.truncate(ranks);
+        if self.remaining.rows() > ranks {
+            self.remaining.truncate_rows(ranks);
+            self.remaining.remove_col(ranks);
+        }
+    }
 }
 
 pub fn summary(probs: &Matrix<f64>) -> Matrix<f64> {
@@ -31,7 +69,136 @@ pub fn summary_no_alloc(
     probs: &Matrix<f64>,
     alloc: &mut Alloc,
 ) {
-    let Alloc { podium, bitmap, summary } = alloc;
+    summary_range_no_alloc(probs, 0..probs.cols(), alloc);
+}
+
+pub fn summary_par(probs: &Matrix<f64>, threads: usize) -> Matrix<f64> {
+    assert!(threads > 0, "number of threads must be greater than 0");
+    let runners = probs.cols();
+    let ranks = probs.rows();
+    let mut allocs = (0..threads)
+        .map(|_| Alloc::new(runners, ranks))
+        .collect::<Vec<_>>();
+    let mut summary = Matrix::allocate(ranks, runners);
+    summary_par_no_alloc(probs, &mut allocs, &mut summary);
+    summary
+}
+
+/// Traverses the podiums on as many threads as there are `allocs`, reducing the partial
+/// summaries into `summary` in the order of `allocs`.
+pub fn summary_par_no_alloc(probs: &Matrix<f64>, allocs: &mut [Alloc], summary: &mut Matrix<f64>) {
+    split_par_no_alloc(probs.cols(), allocs, |first_runners, alloc| {
+        summary_range_no_alloc(probs, first_runners, alloc)
+    });
+    reduce(allocs.iter().map(|alloc| &*alloc.summary), summary);
+}
+
+/// Traverses only the podiums whose first-ranked runner lies in `first_runners`.
+pub fn summary_range_no_alloc(
+    probs: &Matrix<f64>,
+    first_runners: Range<usize>,
+    alloc: &mut Alloc,
+) {
+    summary_admitted_no_alloc(probs, alloc, |rank, runner| {
+        rank > 0 || first_runners.contains(&runner)
+    });
+}
+
+/// Traverses only the podiums in which every runner is admitted at its rank by `admit`,
+/// accumulating their (joint) probabilities into the summary and returning their combined
+/// probability.
+pub(crate) fn summary_admitted_no_alloc(
+    probs: &Matrix<f64>,
+    alloc: &mut Alloc,
+    admit: impl Fn(usize, usize) -> bool,
+) -> f64 {
+    let Alloc { bitmap, remaining, summary, .. } = alloc;
+    debug_assert_eq!(
+        summary.rows(),
+        probs.rows(),
+        "number of rows in the probabilities matrix must equal to the number of rows in the summary matrix"
+    );
+    debug_assert_eq!(
+        summary.cols(),
+        probs.cols(),
+        "number of columns in the probabilities matrix must equal to the number of columns in the summary matrix"
+    );
+    debug_assert_eq!(
+        probs.cols(),
+        bitmap.len(),
+        "number of columns in the probabilities matrix must equal to the bitmap length"
+    );
+    debug_assert!(
+        remaining.rows() == probs.rows() && remaining.cols() == probs.rows(),
+        "remaining matrix must be square, with the number of rows in the probabilities matrix"
+    );
+
+    summary.fill(0.0);
+    if probs.rows() == 0 {
+        return 0.0;
+    }
+    bitmap.clear_all();
+    remaining.row_slice_mut(0).fill(1.0);
+    descend(probs, 0, &admit, 1.0, bitmap, remaining, summary)
+}
+
+/// Places each free, admitted runner at `depth`, returning the combined probability of all podiums
+/// under the current prefix.
+///
+/// Row `depth` of `remaining` holds the unplaced probability mass of each rank (from `depth`
+/// onwards), given the runners placed in the prefix.
+fn descend(
+    probs: &Matrix<f64>,
+    depth: usize,
+    admit: &impl Fn(usize, usize) -> bool,
+    prefix_prob: f64,
+    bitmap: &mut Bitmap,
+    remaining: &mut Matrix<f64>,
+    summary: &mut Matrix<f64>,
+) -> f64 {
+    let ranks = probs.rows();
+    let rank_probs = probs.row_slice(depth);
+    let remaining_prob = remaining[(depth, depth)];
+    let mut total_prob = 0.0;
+    for runner in 0..probs.cols() {
+        if bitmap.get(runner) || !admit(depth, runner) {
+            continue;
+        }
+        let runner_prob = rank_probs[runner];
+        if runner_prob == 0.0 {
+            continue;
+        }
+        let prob = prefix_prob * (runner_prob / remaining_prob);
+        let subtree_prob = if depth + 1 == ranks {
+            prob
+        } else {
+            for rank in depth + 1..ranks {
+                remaining[(depth + 1, rank)] = remaining[(depth, rank)] - probs[(rank, runner)];
+            }
+            bitmap.set(runner);
+            let subtree_prob = descend(probs, depth + 1, admit, prob, bitmap, remaining, summary);
+            bitmap.clear(runner);
+            subtree_prob
+        };
+        summary[(depth, runner)] += subtree_prob;
+        total_prob += subtree_prob;
+    }
+    total_prob
+}
+
+pub fn summary_enumerated(probs: &Matrix<f64>) -> Matrix<f64> {
+    let runners = probs.cols();
+    let ranks = probs.rows();
+    let mut alloc = Alloc::new(runners, ranks);
+    summary_enumerated_no_alloc(probs, &mut alloc);
+    alloc.summary.into_owned()
+}
+
+pub fn summary_enumerated_no_alloc(
+    probs: &Matrix<f64>,
+    alloc: &mut Alloc,
+) {
+    let Alloc { podium, bitmap, summary, .. } = alloc;
     debug_assert_eq!(
         summary.rows(),
         probs.rows(),
@@ -87,25 +254,147 @@ pub fn summary_no_alloc(
     }
 }
 
+/// Equivalent of [`summary_enumerated`], folding the permutations in contiguous chunks on
+/// `threads` threads.
+pub fn summary_enumerated_par(probs: &Matrix<f64>, threads: usize) -> Matrix<f64> {
+    assert!(threads > 0, "number of threads must be greater than 0");
+    let runners = probs.cols();
+    let ranks = probs.rows();
+    let space = Permutations { n: runners, r: ranks };
+    let mut allocs = (0..threads)
+        .map(|_| par::Alloc::new(&space, Matrix::allocate(ranks, runners)))
+        .collect::<Vec<_>>();
+    let mut summary = Matrix::allocate(ranks, runners);
+    summary_enumerated_par_no_alloc(probs, &mut allocs, &mut summary);
+    summary
+}
+
+/// Folds the permutations on as many threads as there are `allocs`, reducing the partial
+/// summaries into `summary` in the order of `allocs`.
+pub fn summary_enumerated_par_no_alloc(
+    probs: &Matrix<f64>,
+    allocs: &mut [par::Alloc<Bitmap, Matrix<f64>>],
+    summary: &mut Matrix<f64>,
+) {
+    let space = Permutations { n: probs.cols(), r: probs.rows() };
+    for alloc in allocs.iter_mut() {
+        alloc.acc.fill(0.0);
+    }
+    fold_par_no_alloc(&space, allocs, |summary, _, podium| {
+        let prob = harville(probs, podium);
+        for (rank, &runner) in podium.iter().enumerate() {
+            summary[(rank, runner)] += prob;
+        }
+    });
+    reduce(allocs.iter().map(|alloc| &alloc.acc), summary);
+}
+
+pub struct MinimalChangeAlloc<'a> {
+    /// The set of podium runners, followed by a sentinel.
+    pub combination: CaptureMut<'a, Vec<usize>, [usize]>,
+    /// The ordering of the set, as positions within `combination`.
+    pub permutation: CaptureMut<'a, Vec<usize>, [usize]>,
+    pub inversions: CaptureMut<'a, Vec<usize>, [usize]>,
+    pub descending: CaptureMut<'a, Bitmap>,
+    /// The Harville factor of each rank.
+    pub factors: CaptureMut<'a, Vec<f64>, [f64]>,
+    pub summary: CaptureMut<'a, Matrix<f64>>,
+}
+
+impl MinimalChangeAlloc<'_> {
+    pub fn new(runners: usize, ranks: usize) -> Self {
+        Self {
+            combination: RevolvingCombiner::alloc(ranks),
+            permutation: vec![0; ranks].into(),
+            inversions: vec![0; ranks].into(),
+            descending: Bitmap::empty(ranks).into(),
+            factors: vec![0.0; ranks].into(),
+            summary: Matrix::allocate(ranks, runners).into(),
+        }
+    }
+}
+
+/// Equivalent of [`summary_enumerated`], visiting the podiums in minimal-change order.
+pub fn summary_minimal_change(probs: &Matrix<f64>) -> Matrix<f64> {
+    let runners = probs.cols();
+    let ranks = probs.rows();
+    let mut alloc = MinimalChangeAlloc::new(runners, ranks);
+    summary_minimal_change_no_alloc(probs, &mut alloc);
+    alloc.summary.into_owned()
+}
+
+pub fn summary_minimal_change_no_alloc(probs: &Matrix<f64>, alloc: &mut MinimalChangeAlloc) {
+    let MinimalChangeAlloc { combination, permutation, inversions, descending, factors, summary } = alloc;
+    debug_assert!(
+        summary.rows() == probs.rows() && summary.cols() == probs.cols(),
+        "summary matrix must have the same dimensions as the probabilities matrix"
+    );
+    debug_assert_eq!(
+        probs.rows(),
+        factors.len(),
+        "number of rows in the probabilities matrix must equal to the number of factors"
+    );
+    summary.fill(0.0);
+    let ranks = probs.rows();
+    if ranks > probs.cols() {
+        return;
+    }
+
+    let mut combiner = RevolvingCombiner::new_no_alloc(probs.cols(), CaptureMut::Borrowed(&mut combination[..]));
+    loop {
+        let runners = combiner.read();
+        let mut permuter = TrotterPermuter::new_no_alloc(trotter_permuter::Alloc {
+            ordinals: CaptureMut::Borrowed(&mut permutation[..]),
+            inversions: CaptureMut::Borrowed(&mut inversions[..]),
+            descending: CaptureMut::Borrowed(&mut **descending),
+        });
+        for rank in 0..ranks {
+            factors[rank] = factor(probs, runners, permuter.read(), rank);
+        }
+        loop {
+            let prob = factors.iter().product::<f64>();
+            for (rank, &ordinal) in permuter.read().iter().enumerate() {
+                summary[(rank, runners[ordinal])] += prob;
+            }
+            if !permuter.advance() {
+                break;
+            }
+            for &rank in permuter.changed() {
+                factors[rank] = factor(probs, runners, permuter.read(), rank);
+            }
+        }
+        if !combiner.advance() {
+            break;
+        }
+    }
+}
+
+/// The Harville factor of the runner at `rank`, given the runners ahead of it. The runner at each
+/// rank is `runners[permutation[rank]]`.
+#[inline(always)]
+fn factor(probs: &Matrix<f64>, runners: &[usize], permutation: &[usize], rank: usize) -> f64 {
+    let rank_probs = probs.row_slice(rank);
+    let mut remaining_prob = 1.;
+    for &ordinal in &permutation[..rank] {
+        remaining_prob -= rank_probs[runners[ordinal]];
+    }
+    rank_probs[runners[permutation[rank]]] / remaining_prob
+}
+
 #[cfg(test)]
 mod tests {
     use assert_float_eq::assert_float_relative_eq;
-    use crate::capture::Capture;
-    use crate::dilative::DilatedProbs;
-    use crate::harville::classic::summary;
+    use crate::comb::par::{self, Permutations};
+    use crate::harville::classic::{summary, summary_enumerated, summary_enumerated_par, summary_enumerated_par_no_alloc, summary_minimal_change, summary_no_alloc, summary_par, Alloc};
     use crate::matrix::Matrix;
-    use crate::testing::assert_slice_f64_relative;
+    use crate::testing::{assert_slice_f64_relative, dilated_probs, dilated_probs_with};
     use crate::probs::SliceExt;
 
     #[test]
     fn harville_summary_3x3_without_scratchings() {
         const WIN_PROBS: [f64; 3] = [0.6, 0.3, 0.1];
         const RANKS: usize = 3;
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_podium_places(RANKS),
-        );
+        let probs = dilated_probs(&WIN_PROBS, RANKS);
         let summary = summary(&probs);
         println!("summary:\n{}", summary.verbose());
         assert_slice_f64_relative(
@@ -137,11 +426,7 @@ mod tests {
     fn harville_summary_3x4_with_scratching() {
         const WIN_PROBS: [f64; 4] = [0.6, 0.3, 0.1, 0.0];
         const RANKS: usize = 3;
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_podium_places(RANKS),
-        );
+        let probs = dilated_probs(&WIN_PROBS, RANKS);
         let summary = summary(&probs);
         println!("summary:\n{}", summary.verbose());
         assert_slice_f64_relative(
@@ -180,11 +465,7 @@ mod tests {
     fn harville_summary_4x4_without_scratchings() {
         const WIN_PROBS: [f64; 4] = [0.4, 0.3, 0.2, 0.1];
         const RANKS: usize = 4;
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_podium_places(RANKS),
-        );
+        let probs = dilated_probs(&WIN_PROBS, RANKS);
         let summary = summary(&probs);
         assert_eq!(RANKS, summary.rows());
         assert_eq!(WIN_PROBS.len(), summary.cols());
@@ -204,11 +485,7 @@ mod tests {
     fn harville_summary_1x4_without_scratchings() {
         const WIN_PROBS: [f64; 4] = [0.4, 0.3, 0.2, 0.1];
         const RANKS: usize = 1;
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_podium_places(RANKS),
-        );
+        let probs = dilated_probs(&WIN_PROBS, RANKS);
         let summary = summary(&probs);
         assert_eq!(RANKS, summary.rows());
         assert_eq!(WIN_PROBS.len(), summary.cols());
@@ -228,11 +505,7 @@ mod tests {
     fn harville_summary_2x4_without_scratchings() {
         const WIN_PROBS: [f64; 4] = [0.4, 0.3, 0.2, 0.1];
         const RANKS: usize = 2;
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_podium_places(RANKS),
-        );
+        let probs = dilated_probs(&WIN_PROBS, RANKS);
         let summary = summary(&probs);
         assert_eq!(RANKS, summary.rows());
         assert_eq!(WIN_PROBS.len(), summary.cols());
@@ -253,11 +526,7 @@ mod tests {
         const WIN_PROBS: [f64; 4] = [0.4, 0.3, 0.2, 0.1];
         const DILATIVES: [f64; 4] = [0.0, 0.1, 0.2, 0.3];
         const RANKS: usize = 4;
-        let probs = Matrix::from(
-            DilatedProbs::default()
-                .with_win_probs(Capture::Borrowed(&WIN_PROBS))
-                .with_dilatives(Capture::Borrowed(&DILATIVES)),
-        );
+        let probs = dilated_probs_with(&WIN_PROBS, &DILATIVES);
         let summary = summary(&probs);
         assert_eq!(RANKS, summary.rows());
         assert_eq!(WIN_PROBS.len(), summary.cols());
@@ -272,4 +541,78 @@ mod tests {
             assert_float_relative_eq!(1.0, col_cells.sum::<f64>());
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn harville_summary_matches_enumerated() {
+        fn assert_matches(win_probs: &[f64], dilatives: &[f64]) {
+            let probs = dilated_probs_with(win_probs, dilatives);
+            let expected = summary_enumerated(&probs);
+            let actual = summary(&probs);
+            println!("summary:\n{}", actual.verbose());
+            assert_slice_f64_relative(expected.flatten(), actual.flatten(), 1e-12);
+        }
+
+        assert_matches(&[0.4, 0.3, 0.2, 0.1], &[0.0; 4]);
+        assert_matches(&[0.4, 0.3, 0.2, 0.1], &[0.0; 1]);
+        assert_matches(&[0.4, 0.3, 0.2, 0.1], &[0.0, 0.1, 0.2, 0.3]);
+        assert_matches(&[0.6, 0.3, 0.1, 0.0], &[0.0; 3]);
+        assert_matches(&[0.25, 0.2, 0.15, 0.12, 0.1, 0.08, 0.06, 0.04], &[0.0, 0.1, 0.2, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn harville_summary_enumerated_par() {
+        let probs = dilated_probs_with(&[0.25, 0.2, 0.15, 0.12, 0.1, 0.08, 0.06, 0.04], &[0.0, 0.1, 0.2]);
+        let expected = summary_enumerated(&probs);
+        for threads in [1, 3, 8] {
+            assert_slice_f64_relative(expected.flatten(), summary_enumerated_par(&probs, threads).flatten(), 1e-12);
+        }
+    }
+
+    #[test]
+    fn harville_summary_minimal_change() {
+        let probs = dilated_probs_with(&[0.25, 0.2, 0.15, 0.12, 0.1, 0.08, 0.06, 0.04], &[0.0, 0.1, 0.2]);
+        assert_slice_f64_relative(summary_enumerated(&probs).flatten(), summary_minimal_change(&probs).flatten(), 1e-12);
+
+        let probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1], 4);
+        assert_slice_f64_relative(summary_enumerated(&probs).flatten(), summary_minimal_change(&probs).flatten(), 1e-12);
+    }
+
+    #[test]
+    fn harville_summary_enumerated_par_reuse_alloc() {
+        let probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1], 3);
+        let space = Permutations { n: 4, r: 3 };
+        let mut allocs = (0..3)
+            .map(|_| par::Alloc::new(&space, Matrix::allocate(3, 4)))
+            .collect::<Vec<_>>();
+        let mut summary = Matrix::allocate(3, 4);
+        summary_enumerated_par_no_alloc(&probs, &mut allocs, &mut summary);
+        let first = summary.clone();
+        summary_enumerated_par_no_alloc(&probs, &mut allocs, &mut summary);
+        assert_eq!(first, summary);
+        assert_slice_f64_relative(summary_enumerated(&probs).flatten(), summary.flatten(), 1e-12);
+    }
+
+    #[test]
+    fn harville_summary_reuse_alloc() {
+        const WIN_PROBS: [f64; 4] = [0.4, 0.3, 0.2, 0.1];
+        let probs = dilated_probs(&WIN_PROBS, 3);
+        let mut alloc = Alloc::new(4, 3);
+        summary_no_alloc(&probs, &mut alloc);
+        let first = (*alloc.summary).clone();
+        summary_no_alloc(&probs, &mut alloc);
+        assert_eq!(first, *alloc.summary);
+    }
+
+    #[test]
+    fn harville_summary_par() {
+        const WIN_PROBS: [f64; 6] = [0.3, 0.25, 0.2, 0.12, 0.08, 0.05];
+        const DILATIVES: [f64; 4] = [0.0, 0.1, 0.2, 0.3];
+        let probs = dilated_probs_with(&WIN_PROBS, &DILATIVES);
+        let expected = summary(&probs);
+        for threads in [1, 2, 4, 7] {
+            let actual = summary_par(&probs, threads);
+            assert_slice_f64_relative(expected.flatten(), actual.flatten(), 1e-12);
+            assert_eq!(actual, summary_par(&probs, threads));
+        }
+    }
+}
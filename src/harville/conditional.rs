@@ -0,0 +1,189 @@
+//! Rank probabilities conditional on a partial result.
+//!
+//! Given partial knowledge of the finishing order — e.g., that runner 3 finished first, or that
+//! runners 1 and 5 are not in the top 3 — the conditional summary is obtained by traversing only
+//! the podiums consistent with the [`Condition`]s and normalising by their combined
+//! probability. Because the Harville product factorises by rank, the traversal shares the
+//! depth-first machinery of [`classic`], pruning every inadmissible subtree.
+
+use crate::harville::classic;
+use crate::matrix::Matrix;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    /// The `runner` finished in the given (zero-based) `rank`.
+    Finished { runner: usize, rank: usize },
+
+    /// The `runner` did not finish in any of the first `places` ranks.
+    NotInTop { runner: usize, places: usize },
+}
+
+impl Condition {
+    /// Whether the condition admits the `runner` in the given `rank`.
+    #[inline]
+    pub fn admits(&self, rank: usize, runner: usize) -> bool {
+        match *self {
+            Condition::Finished {
+                runner: finisher,
+                rank: finished_rank,
+            } => (rank == finished_rank) == (runner == finisher),
+            Condition::NotInTop {
+                runner: excluded,
+                places,
+            } => runner != excluded || rank >= places,
+        }
+    }
+}
+
+/// The summary conditional on all of the `conditions` holding, or `None` if the conditions have
+/// zero probability.
+pub fn summary(probs: &Matrix<f64>, conditions: &[Condition]) -> Option<Matrix<f64>> {
+    let runners = probs.cols();
+    let ranks = probs.rows();
+    let mut alloc = classic::Alloc::new(runners, ranks);
+    let prob = summary_no_alloc(probs, conditions, &mut alloc);
+    if prob > 0.0 {
+        Some(alloc.summary.into_owned())
+    } else {
+        None
+    }
+}
+
+/// Computes the conditional summary into the `alloc`, returning the (unconditional) probability
+/// of the `conditions`. If the latter is zero, the summary is left zeroed.
+pub fn summary_no_alloc(probs: &Matrix<f64>, conditions: &[Condition], alloc: &mut classic::Alloc) -> f64 {
+    for condition in conditions {
+        validate_condition(probs, condition);
+    }
+    let prob = classic::summary_admitted_no_alloc(probs, alloc, |rank, runner| {
+        conditions
+            .iter()
+            .all(|condition| condition.admits(rank, runner))
+    });
+    if prob > 0.0 {
+        for cell in alloc.summary.flatten_mut() {
+            *cell /= prob;
+        }
+    }
+    prob
+}
+
+#[inline]
+fn validate_condition(probs: &Matrix<f64>, condition: &Condition) {
+    match *condition {
+        Condition::Finished { runner, rank } => {
+            assert!(
+                runner < probs.cols(),
+                "runner {runner} is out of bounds for {} runners",
+                probs.cols()
+            );
+            assert!(
+                rank < probs.rows(),
+                "rank {rank} is out of bounds for {} ranks",
+                probs.rows()
+            );
+        }
+        Condition::NotInTop { runner, places } => {
+            assert!(
+                runner < probs.cols(),
+                "runner {runner} is out of bounds for {} runners",
+                probs.cols()
+            );
+            assert!(
+                places <= probs.rows(),
+                "places ({places}) cannot exceed the number of ranks ({})",
+                probs.rows()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probs::SliceExt;
+    use crate::testing::{assert_slice_f64_relative, dilated_probs};
+    use assert_float_eq::*;
+
+    #[test]
+    fn finished_first() {
+        let probs = dilated_probs(&[0.5, 0.3, 0.2], 3);
+        let summary = summary(&probs, &[Condition::Finished { runner: 0, rank: 0 }]).unwrap();
+        println!("summary:\n{}", summary.verbose());
+        assert_slice_f64_relative(
+            &[1.0, 0.0, 0.0, 0.0, 0.6, 0.4, 0.0, 0.4, 0.6],
+            summary.flatten(),
+            1e-12,
+        );
+    }
+
+    #[test]
+    fn finished_second() {
+        let probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1], 4);
+        let summary = summary(&probs, &[Condition::Finished { runner: 3, rank: 1 }]).unwrap();
+        assert_slice_f64_relative(&[0.0, 1.0, 0.0, 0.0], &summary.col(3).copied().collect::<Vec<_>>(), 1e-12);
+        assert_eq!(1.0, summary[(1, 3)]);
+        assert_float_relative_eq!(1.0, summary.row_slice(0).sum());
+
+        // P(0 wins, 3 second) / P(1 wins, 3 second)
+        assert_float_relative_eq!((0.4 / 0.6) / (0.3 / 0.7), summary[(0, 0)] / summary[(0, 1)]);
+    }
+
+    #[test]
+    fn not_in_top() {
+        let probs = dilated_probs(&[0.3, 0.25, 0.2, 0.15, 0.1], 3);
+        let unconditional = classic::summary(&probs);
+        let mut alloc = classic::Alloc::new(5, 3);
+        let prob = summary_no_alloc(
+            &probs,
+            &[Condition::NotInTop { runner: 1, places: 3 }],
+            &mut alloc,
+        );
+        assert_float_relative_eq!(1.0 - unconditional.col(1).sum::<f64>(), prob);
+        assert_eq!(0.0, alloc.summary.col(1).sum::<f64>());
+        for row in alloc.summary.into_iter() {
+            assert_float_relative_eq!(1.0, row.sum());
+        }
+    }
+
+    #[test]
+    fn combined_conditions() {
+        let probs = dilated_probs(&[0.3, 0.25, 0.2, 0.15, 0.1], 3);
+        let conditions = [
+            Condition::Finished { runner: 3, rank: 0 },
+            Condition::NotInTop { runner: 1, places: 3 },
+            Condition::NotInTop { runner: 4, places: 2 },
+        ];
+        let summary = summary(&probs, &conditions).unwrap();
+        assert_eq!(1.0, summary[(0, 3)]);
+        assert_eq!(0.0, summary.col(1).sum::<f64>());
+        assert_eq!(0.0, summary[(1, 4)]);
+        assert!(summary[(2, 4)] > 0.0);
+        for row in summary.into_iter() {
+            assert_float_relative_eq!(1.0, row.sum());
+        }
+    }
+
+    #[test]
+    fn impossible_conditions() {
+        let probs = dilated_probs(&[0.5, 0.3, 0.2, 0.0], 2);
+        assert_eq!(
+            None,
+            summary(
+                &probs,
+                &[
+                    Condition::Finished { runner: 0, rank: 0 },
+                    Condition::Finished { runner: 1, rank: 0 },
+                ]
+            )
+        );
+        assert_eq!(None, summary(&probs, &[Condition::Finished { runner: 3, rank: 1 }]));
+    }
+
+    #[test]
+    #[should_panic(expected = "places (3) cannot exceed the number of ranks (2)")]
+    fn places_out_of_bounds() {
+        let probs = dilated_probs(&[0.5, 0.3, 0.2], 2);
+        summary(&probs, &[Condition::NotInTop { runner: 0, places: 3 }]);
+    }
+}
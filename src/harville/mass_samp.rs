@@ -1,14 +1,16 @@
 use crate::capture::CaptureMut;
+use crate::comb::bitmap::Bitmap;
 use crate::stream::generator::Generator;
 use crate::comb::permuter::Permuter;
-use crate::comb::{count_permutations, permuter};
-use crate::harville::harville;
+use crate::comb::{checked_count_permutations, count_permutations, permuter};
+use crate::comb::par::split_par_no_alloc;
+use crate::harville::{count_sampled, finalise_sampled, for_each_sampled, harville, reduce, scratch_col};
 use crate::matrix::Matrix;
-use crate::probs::SliceExt;
+use std::ops::Range;
 
 pub struct Alloc<'a> {
     pub podium: CaptureMut<'a, Vec<usize>, [usize]>,
-    pub bitmap: CaptureMut<'a, Vec<bool>, [bool]>,
+    pub bitmap: CaptureMut<'a, Bitmap>,
     pub sorted_runners: CaptureMut<'a, Vec<usize>, [usize]>,
     pub sans_self_runners: CaptureMut<'a, Vec<usize>, [usize]>,
     pub sans_self_podium: CaptureMut<'a, Vec<usize>, [usize]>,
@@ -19,13 +21,29 @@ impl<'a> Alloc<'a> {
     pub fn new(runners: usize, ranks: usize) -> Self {
         Self {
             podium: vec![0; ranks].into(),
-            bitmap: vec![false; runners - 1].into(),
+            bitmap: Bitmap::empty(runners - 1).into(),
             sorted_runners: vec![0; runners].into(),
             sans_self_runners: vec![0; runners - 1].into(),
             sans_self_podium: vec![0; ranks - 1].into(),
             summary: Matrix::allocate(ranks, runners).into(),
         }
     }
+
+    /// Withdraws the `runner` from the allocation without reallocating, to suit a probabilities
+    /// matrix from which the runner has been [scratched](crate::harville::scratch).
+    ///
+    /// Only the buffers are shrunk; no sampled podiums are carried over, since the next summary is
+    /// sampled afresh over the remaining runners. At least one runner must remain.
+    pub fn scratch(&mut self, runner: usize) {
+        assert!(self.summary.cols() > 1, "cannot scratch the only remaining runner");
+        let ranks = scratch_col(&mut self.summary, runner);
+        let runners = self.summary.cols();
+        self.podium.truncate(ranks);
+        self.bitmap.truncate(runners - 1);
+        self.sorted_runners.truncate(runners);
+        self.sans_self_runners.truncate(runners - 1);
+        self.sans_self_podium.truncate(ranks - 1);
+    }
 }
 
 pub fn summary(probs: &Matrix<f64>, degree: usize) -> Matrix<f64> {
@@ -37,6 +55,65 @@ pub fn summary(probs: &Matrix<f64>, degree: usize) -> Matrix<f64> {
 }
 
 pub fn summary_no_alloc(probs: &Matrix<f64>, degree: usize, alloc: &mut Alloc) {
+    let podiums = count_sampled_podiums(probs.cols(), probs.rows(), degree);
+    summary_range_no_alloc(probs, degree, 0..podiums, alloc);
+    finalise_sampled(probs, &mut alloc.summary);
+}
+
+pub fn summary_par(probs: &Matrix<f64>, degree: usize, threads: usize) -> Matrix<f64> {
+    assert!(threads > 0, "number of threads must be greater than 0");
+    let runners = probs.cols();
+    let ranks = probs.rows();
+    let mut allocs = (0..threads)
+        .map(|_| Alloc::new(runners, ranks))
+        .collect::<Vec<_>>();
+    let mut summary = Matrix::allocate(ranks, runners);
+    summary_par_no_alloc(probs, degree, &mut allocs, &mut summary);
+    summary
+}
+
+/// Splits the sampled podiums into contiguous index ranges, one for each of the `allocs`,
+/// sampling each range on its own thread and reducing the partial summaries into `summary` in
+/// the order of `allocs`. The outcome equals that of [`summary_no_alloc`], up to the rounding of
+/// the partial sums.
+pub fn summary_par_no_alloc(
+    probs: &Matrix<f64>,
+    degree: usize,
+    allocs: &mut [Alloc],
+    summary: &mut Matrix<f64>,
+) {
+    let podiums = count_sampled_podiums(probs.cols(), probs.rows(), degree);
+    split_par_no_alloc(podiums, allocs, |podiums, alloc| {
+        summary_range_no_alloc(probs, degree, podiums, alloc)
+    });
+    reduce(allocs.iter().map(|alloc| &*alloc.summary), summary);
+    finalise_sampled(probs, summary);
+}
+
+/// The number of podiums sampled for the given number of `runners` and `ranks`, being the range
+/// of indices accepted by [`summary_range_no_alloc`].
+pub fn count_sampled_podiums(runners: usize, ranks: usize, degree: usize) -> usize {
+    count_sampled(runners, ranks, |rank| rank_quota(runners, degree, rank))
+}
+
+/// The number of podiums sampled for each runner in the given `rank`: the leading permutations of
+/// the other runners in lexicographic order, capped by the `degree`.
+#[inline]
+fn rank_quota(runners: usize, degree: usize, rank: usize) -> usize {
+    let quota = count_permutations(runners - 1, degree - 1) + 1;
+    checked_count_permutations(runners - 1, rank).map_or(quota, |permutations| permutations.min(quota))
+}
+
+/// Accumulates the unnormalised sampled probabilities of the given range of `podiums` (indexed as
+/// per [`count_sampled_podiums`]) for ranks 1 onwards, leaving the remaining cells zeroed. Each
+/// block of podiums sharing a rank and a runner is resumed from its first index, rather than
+/// traversed from the start.
+pub fn summary_range_no_alloc(
+    probs: &Matrix<f64>,
+    degree: usize,
+    podiums: Range<usize>,
+    alloc: &mut Alloc,
+) {
     let Alloc {
         podium,
         bitmap,
@@ -83,6 +160,7 @@ pub fn summary_no_alloc(probs: &Matrix<f64>, degree: usize, alloc: &mut Alloc) {
 
     let ranks = probs.rows();
     let runners = probs.cols();
+    summary.fill(0.0);
 
     // initialise runner list for sorting
     for runner in 0..runners {
@@ -96,12 +174,14 @@ pub fn summary_no_alloc(probs: &Matrix<f64>, degree: usize, alloc: &mut Alloc) {
         b_prob.total_cmp(&a_prob)
     });
 
-    let quota = count_permutations(runners - 1, degree - 1);
-    for rank in 1..ranks {
-        let sans_self_podium = &mut sans_self_podium[..rank];
-        let podium = &mut podium[..rank + 1];
-
-        for runner in 0..runners {
+    for_each_sampled(
+        runners,
+        ranks,
+        |rank| rank_quota(runners, degree, rank),
+        podiums,
+        |rank, runner, permutations| {
+            let sans_self_podium = &mut sans_self_podium[..rank];
+            let podium = &mut podium[..rank + 1];
             for (ss_index, ss_runner) in sorted_runners
                 .iter()
                 .filter(|&&index| index != runner)
@@ -109,48 +189,28 @@ pub fn summary_no_alloc(probs: &Matrix<f64>, degree: usize, alloc: &mut Alloc) {
             {
                 sans_self_runners[ss_index] = *ss_runner;
             }
-            let total_permutations = count_permutations(runners - 1, rank);
             log::trace!(
-                "runner: {runner}: rank: {rank}, total_perms: {total_permutations}, quota: {quota}, sans_self_runners: {sans_self_runners:?}"
+                "runner: {runner}: rank: {rank}, perms: {permutations:?}, sans_self_runners: {sans_self_runners:?}"
             );
 
-            let mut permuter = Permuter::new_no_alloc(
+            let mut permuter = Permuter::new_at_no_alloc(
                 rank,
+                permutations.start,
                 permuter::Alloc {
                     bitmap: CaptureMut::Borrowed(bitmap),
                     ordinals: CaptureMut::Borrowed(sans_self_podium),
                 },
             );
-            let mut permutation = 0;
-            loop {
-                for (index, ordinal) in &mut permuter.read().iter().enumerate() {
+            for _ in permutations {
+                for (index, ordinal) in permuter.read().iter().enumerate() {
                     podium[index] = sans_self_runners[*ordinal];
                 }
                 podium[rank] = runner;
                 let prob = harville(probs, podium);
                 log::trace!("  podium: {podium:?}, prob: {prob:.6}");
                 summary[(rank, runner)] += prob;
-
-                if permutation == quota {
-                    break;
-                }
-
-                if !permuter.advance() {
-                    break;
-                }
-
-                permutation += 1;
+                permuter.advance();
             }
-        }
-    }
-
-    // assign the win probs from the source to the first row
-    for (index, prob) in summary.row_slice_mut(0).iter_mut().enumerate() {
-        *prob = probs[(0, index)]
-    }
-
-    // normalise remaining rows (2nd rank onwards)
-    for row_idx in 1..summary.rows() {
-        summary.row_slice_mut(row_idx).normalise(1.0);
-    }
+        },
+    );
 }
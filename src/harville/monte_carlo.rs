@@ -0,0 +1,260 @@
+//! Monte Carlo simulation of finishing orders under the (dilated) Harville model.
+//!
+//! Each trial draws a complete podium rank by rank: the runner taking rank `r` is drawn from the
+//! runners yet to be placed, in proportion to their probabilities in row `r` of the dilated
+//! matrix — a Plackett–Luce draw. The draw uses the Gumbel-max trick, selecting the unplaced
+//! runner that maximises <code>ln p<sub>r,j</sub> + G<sub>j</sub></code>, where each
+//! <code>G<sub>j</sub></code> is an independent standard Gumbel sample.
+//!
+//! Every cell of the resulting summary is a proportion over `N` trials, with standard error
+//! <code>√(p(1 - p) / N)</code>. Trials are run in batches until the largest standard error falls
+//! to the target in the [`Config`], or the trial limit is reached.
+
+use crate::capture::CaptureMut;
+use crate::comb::bitmap::Bitmap;
+use crate::matrix::Matrix;
+use crate::random::gumbel;
+use tinyrand::{Rand, StdRand};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Simulation stops once the standard error of every cell is at or below this value.
+    pub target_std_error: f64,
+
+    /// Number of trials run between successive checks of the standard error.
+    pub batch_trials: u64,
+
+    /// Simulation stops after this many trials, irrespective of the standard error.
+    pub max_trials: u64,
+}
+
+impl Config {
+    pub fn validate(&self) {
+        assert!(self.target_std_error >= 0.0, "target standard error must be non-negative");
+        assert!(self.batch_trials > 0, "number of trials per batch must be positive");
+        assert!(self.max_trials > 0, "maximum number of trials must be positive");
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            target_std_error: 0.001,
+            batch_trials: 10_000,
+            max_trials: 10_000_000,
+        }
+    }
+}
+
+pub struct Alloc<'a, R: Rand> {
+    pub log_probs: CaptureMut<'a, Matrix<f64>>,
+    pub bitmap: CaptureMut<'a, Bitmap>,
+    pub counts: CaptureMut<'a, Matrix<u64>>,
+    pub rand: CaptureMut<'a, R>,
+    pub summary: CaptureMut<'a, Matrix<f64>>,
+    pub std_errors: CaptureMut<'a, Matrix<f64>>,
+}
+
+impl Alloc<'_, StdRand> {
+    pub fn new(runners: usize, ranks: usize) -> Self {
+        Self {
+            log_probs: Matrix::allocate(ranks, runners).into(),
+            bitmap: Bitmap::empty(runners).into(),
+            counts: Matrix::allocate(ranks, runners).into(),
+            rand: StdRand::default().into(),
+            summary: Matrix::allocate(ranks, runners).into(),
+            std_errors: Matrix::allocate(ranks, runners).into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    pub summary: Matrix<f64>,
+    pub std_errors: Matrix<f64>,
+    pub trials: u64,
+}
+
+impl Estimate {
+    /// The largest standard error across all cells.
+    pub fn max_std_error(&self) -> f64 {
+        max_std_error(&self.std_errors)
+    }
+}
+
+pub fn simulate(probs: &Matrix<f64>, config: &Config) -> Estimate {
+    let runners = probs.cols();
+    let ranks = probs.rows();
+    let mut alloc = Alloc::new(runners, ranks);
+    let trials = simulate_no_alloc(probs, config, &mut alloc);
+    Estimate {
+        summary: alloc.summary.into_owned(),
+        std_errors: alloc.std_errors.into_owned(),
+        trials,
+    }
+}
+
+/// Simulates in batches until the target standard error or the trial limit is reached, returning
+/// the number of trials run. The estimated summary and its standard errors are left in the
+/// `alloc`.
+pub fn simulate_no_alloc<R: Rand>(probs: &Matrix<f64>, config: &Config, alloc: &mut Alloc<R>) -> u64 {
+    config.validate();
+    let Alloc {
+        log_probs,
+        bitmap,
+        counts,
+        rand,
+        summary,
+        std_errors,
+    } = alloc;
+    debug_assert_eq!(
+        summary.rows(),
+        probs.rows(),
+        "number of rows in the probabilities matrix must equal to the number of rows in the summary matrix"
+    );
+    debug_assert_eq!(
+        summary.cols(),
+        probs.cols(),
+        "number of columns in the probabilities matrix must equal to the number of columns in the summary matrix"
+    );
+    debug_assert!(
+        log_probs.rows() == probs.rows() && log_probs.cols() == probs.cols(),
+        "log probabilities matrix must have the same dimensions as the probabilities matrix"
+    );
+    debug_assert!(
+        counts.rows() == probs.rows() && counts.cols() == probs.cols(),
+        "counts matrix must have the same dimensions as the probabilities matrix"
+    );
+    debug_assert!(
+        std_errors.rows() == probs.rows() && std_errors.cols() == probs.cols(),
+        "standard errors matrix must have the same dimensions as the probabilities matrix"
+    );
+    debug_assert_eq!(
+        probs.cols(),
+        bitmap.len(),
+        "number of columns in the probabilities matrix must equal to the bitmap length"
+    );
+
+    for (log_prob, prob) in log_probs.flatten_mut().iter_mut().zip(probs.flatten()) {
+        *log_prob = prob.ln();
+    }
+    counts.fill(0);
+
+    let mut trials = 0;
+    loop {
+        let batch_trials = config.batch_trials.min(config.max_trials - trials);
+        for _ in 0..batch_trials {
+            trial(log_probs, bitmap, &mut **rand, counts);
+        }
+        trials += batch_trials;
+
+        for ((prob, std_error), &count) in summary
+            .flatten_mut()
+            .iter_mut()
+            .zip(std_errors.flatten_mut())
+            .zip(counts.flatten())
+        {
+            *prob = count as f64 / trials as f64;
+            *std_error = (*prob * (1.0 - *prob) / trials as f64).sqrt();
+        }
+        log::trace!("trials: {trials}, max std error: {:.6}", max_std_error(std_errors));
+
+        if trials == config.max_trials || max_std_error(std_errors) <= config.target_std_error {
+            return trials;
+        }
+    }
+}
+
+/// Draws a single podium, incrementing the count of each (rank, runner) placement.
+#[inline]
+fn trial(log_probs: &Matrix<f64>, bitmap: &mut Bitmap, rand: &mut impl Rand, counts: &mut Matrix<u64>) {
+    bitmap.clear_all();
+    for rank in 0..log_probs.rows() {
+        let mut best_runner = None;
+        let mut best_key = f64::NEG_INFINITY;
+        for (runner, &log_prob) in log_probs.row_slice(rank).iter().enumerate() {
+            if bitmap.get(runner) || log_prob == f64::NEG_INFINITY {
+                continue;
+            }
+            let key = log_prob + gumbel(rand);
+            if key > best_key {
+                best_key = key;
+                best_runner = Some(runner);
+            }
+        }
+        match best_runner {
+            Some(runner) => {
+                bitmap.set(runner);
+                counts[(rank, runner)] += 1;
+            }
+            None => break,
+        }
+    }
+}
+
+#[inline]
+fn max_std_error(std_errors: &Matrix<f64>) -> f64 {
+    std_errors.flatten().iter().copied().fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harville::subset_dp;
+    use crate::probs::SliceExt;
+    use crate::testing::{dilated_probs, dilated_probs_with};
+    use assert_float_eq::*;
+
+    #[test]
+    fn simulate_within_error_bars() {
+        let probs = dilated_probs_with(&[0.4, 0.3, 0.2, 0.1, 0.0], &[0.0, 0.1, 0.2]);
+        let config = Config {
+            target_std_error: 0.002,
+            ..Config::default()
+        };
+        let estimate = simulate(&probs, &config);
+        println!("summary:\n{}", estimate.summary.verbose());
+        assert!(estimate.max_std_error() <= 0.002);
+        assert!(estimate.trials < config.max_trials);
+        assert_eq!(0, estimate.trials % config.batch_trials);
+
+        let exact = subset_dp::summary(&probs);
+        for (&expected, (&actual, &std_error)) in exact
+            .flatten()
+            .iter()
+            .zip(estimate.summary.flatten().iter().zip(estimate.std_errors.flatten()))
+        {
+            assert!(
+                (expected - actual).abs() <= 5.0 * std_error.max(1e-9),
+                "expected: {expected}, actual: {actual}, std error: {std_error}"
+            );
+        }
+        for row in estimate.summary.into_iter() {
+            assert_float_absolute_eq!(1.0, row.sum(), 1e-9);
+        }
+        assert_eq!(0.0, estimate.summary.col(4).sum::<f64>());
+    }
+
+    #[test]
+    fn simulate_stops_at_max_trials() {
+        let probs = dilated_probs(&[0.5, 0.3, 0.2], 2);
+        let config = Config {
+            target_std_error: 0.0,
+            batch_trials: 300,
+            max_trials: 1_000,
+        };
+        let estimate = simulate(&probs, &config);
+        assert_eq!(1_000, estimate.trials);
+        assert!(estimate.max_std_error() > 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "number of trials per batch must be positive")]
+    fn config_without_batch_trials() {
+        Config {
+            batch_trials: 0,
+            ..Config::default()
+        }
+        .validate();
+    }
+}
@@ -1,14 +1,17 @@
 use crate::capture::CaptureMut;
-use crate::comb::{count_permutations, pick_permutation};
-use crate::harville::harville;
+use crate::comb::count_permutations;
+use crate::comb::fenwick::{self, pick_permutation_fenwick};
+use crate::comb::par::{split_par_no_alloc, split_range};
+use crate::harville::{harville, reduce, scratch_col};
 use crate::matrix::Matrix;
 use crate::probs::SliceExt;
 use std::cmp::max;
-use tinyrand::{Rand, StdRand};
+use std::ops::Range;
+use tinyrand::{Rand, Seeded, StdRand};
 
 pub struct Alloc<'a, R: Rand> {
     pub podium: CaptureMut<'a, Vec<usize>, [usize]>,
-    pub bitmap:  CaptureMut<'a, Vec<bool>, [bool]>,
+    pub tree: CaptureMut<'a, Vec<usize>, [usize]>,
     pub rand: CaptureMut<'a, R>,
     pub summary:  CaptureMut<'a, Matrix<f64>>,
 }
@@ -17,13 +20,27 @@ impl<'a> Alloc<'a, StdRand> {
     pub fn new(runners: usize, ranks: usize) -> Self {
         Self {
             podium: vec![0; ranks].into(),
-            bitmap: vec![false; runners].into(),
+            tree: vec![0; runners].into(),
             rand: StdRand::default().into(),
             summary: Matrix::allocate(ranks, runners).into(),
         }
     }
 }
 
+impl<R: Rand> Alloc<'_, R> {
+    /// Withdraws the `runner` from the allocation without reallocating, to suit a probabilities
+    /// matrix from which the runner has been [scratched](crate::harville::scratch).
+    ///
+    /// Only the buffers are shrunk; no sampled podiums are carried over, since the next summary is
+    /// sampled afresh over the remaining runners. At least one runner must remain.
+    pub fn scratch(&mut self, runner: usize) {
+        assert!(self.summary.cols() > 1, "cannot scratch the only remaining runner");
+        let ranks = scratch_col(&mut self.summary, runner);
+        self.podium.truncate(ranks);
+        self.tree.truncate(self.tree.len() - 1);
+    }
+}
+
 pub fn summary(probs: &Matrix<f64>, degree: usize) -> Matrix<f64> {
     let runners = probs.cols();
     let ranks = probs.rows();
@@ -37,7 +54,60 @@ pub fn summary_no_alloc<R: Rand>(
     degree: usize,
     alloc: &mut Alloc<R>,
 ) {
-    let Alloc { podium, bitmap, rand, summary } = alloc;
+    let total_permutations = count_permutations(probs.cols(), probs.rows());
+    summary_range_no_alloc(probs, degree, 0..total_permutations, alloc);
+    finalise(probs, degree, &mut alloc.summary);
+}
+
+/// Samples the permutation space on `threads` threads, seeding the random number generator of
+/// each thread with the start of its range of permutations.
+///
+/// The jumps between sampled permutations are drawn from a separate sequence in each range, so
+/// the sampled permutations (and hence the outcome) vary with the number of threads. For a given
+/// number of threads, the outcome is reproducible; on a single thread, it equals that of
+/// [`summary`].
+pub fn summary_par(probs: &Matrix<f64>, degree: usize, threads: usize) -> Matrix<f64> {
+    assert!(threads > 0, "number of threads must be greater than 0");
+    let runners = probs.cols();
+    let ranks = probs.rows();
+    let total_permutations = count_permutations(runners, ranks);
+    let mut allocs = split_range(total_permutations, threads)
+        .map(|permutations| Alloc {
+            rand: StdRand::seed(permutations.start as u64).into(),
+            ..Alloc::new(runners, ranks)
+        })
+        .collect::<Vec<_>>();
+    let mut summary = Matrix::allocate(ranks, runners);
+    summary_par_no_alloc(probs, degree, &mut allocs, &mut summary);
+    summary
+}
+
+/// Splits the permutation space into contiguous index ranges, one for each of the `allocs`,
+/// sampling each range on its own thread and reducing the partial summaries into `summary` in
+/// the order of `allocs`. Each range is sampled with the random number generator of its allocation.
+pub fn summary_par_no_alloc<R: Rand + Send>(
+    probs: &Matrix<f64>,
+    degree: usize,
+    allocs: &mut [Alloc<R>],
+    summary: &mut Matrix<f64>,
+) {
+    let total_permutations = count_permutations(probs.cols(), probs.rows());
+    split_par_no_alloc(total_permutations, allocs, |permutations, alloc| {
+        summary_range_no_alloc(probs, degree, permutations, alloc)
+    });
+    reduce(allocs.iter().map(|alloc| &*alloc.summary), summary);
+    finalise(probs, degree, summary);
+}
+
+/// Accumulates the unnormalised probabilities of the podiums sampled from the given range of
+/// `permutations`.
+pub fn summary_range_no_alloc<R: Rand>(
+    probs: &Matrix<f64>,
+    degree: usize,
+    permutations: Range<usize>,
+    alloc: &mut Alloc<R>,
+) {
+    let Alloc { podium, tree, rand, summary } = alloc;
     debug_assert_eq!(
         summary.rows(),
         probs.rows(),
@@ -55,19 +125,19 @@ pub fn summary_no_alloc<R: Rand>(
     );
     debug_assert_eq!(
         probs.cols(),
-        bitmap.len(),
-        "number of columns in the probabilities matrix must equal to the bitmap length"
+        tree.len(),
+        "number of columns in the probabilities matrix must equal to the tree length"
     );
     let ranks = probs.rows();
     let runners = probs.cols();
-    let total_permutations = count_permutations(runners, ranks);
-    let capped_permutations = runners.pow(degree as u32);
-    let step = max(1, total_permutations / capped_permutations);
+    let step = step(runners, ranks, degree);
+    summary.fill(0.0);
+    fenwick::reset(tree);
 
-    let mut permutation = 0;
+    let mut permutation = permutations.start;
     let mut evaluated = 0;
-    while permutation < total_permutations {
-        pick_permutation(runners, permutation, bitmap, podium);
+    while permutation < permutations.end {
+        pick_permutation_fenwick(runners, permutation, tree, podium);
         let jump = if step > 1 {
             rand.next_usize() % (step * 2) + 1
         } else {
@@ -81,11 +151,23 @@ pub fn summary_no_alloc<R: Rand>(
         }
     }
     log::trace!(
-        "runners: {runners}, ranks: {ranks}, degree: {degree}, total perms: {total_permutations}, capped perms: {capped_permutations}, step: {step}, evaluated: {evaluated} ({:.6}%)",
-        evaluated as f64 / total_permutations as f64 * 100.0
+        "runners: {runners}, ranks: {ranks}, degree: {degree}, perms: {permutations:?}, step: {step}, evaluated: {evaluated} ({:.6}%)",
+        evaluated as f64 / permutations.len() as f64 * 100.0
     );
+}
+
+/// The mean distance between sampled permutations, such that roughly `runners^degree`
+/// permutations are sampled in total.
+#[inline]
+fn step(runners: usize, ranks: usize, degree: usize) -> usize {
+    let total_permutations = count_permutations(runners, ranks);
+    let capped_permutations = runners.pow(degree as u32);
+    max(1, total_permutations / capped_permutations)
+}
 
-    if step > 1 {
+/// Normalises the rows of a sampled `summary`, unless every permutation was evaluated.
+fn finalise(probs: &Matrix<f64>, degree: usize, summary: &mut Matrix<f64>) {
+    if step(probs.cols(), probs.rows(), degree) > 1 {
         for row_idx in 0..summary.rows() {
             summary.row_slice_mut(row_idx).normalise(1.0);
         }
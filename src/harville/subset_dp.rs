@@ -0,0 +1,297 @@
+//! Exact Harville summaries by dynamic programming over subsets of placed runners.
+//!
+//! Under Harville, the probability of runner `j` taking rank `r` depends only on the _set_ of
+//! runners occupying ranks `0..r`, not on their order. The probability of each such set is
+//! accumulated level-by-level (one level per rank), sharing the prefix products between all
+//! podiums that place the same runners. The number of states is
+//! <sup>n</sup>C<sub>0</sub> + … + <sup>n</sup>C<sub>k-1</sub>, as opposed to the
+//! <sup>n</sup>P<sub>k</sub> podiums traversed by [`classic`](super::classic).
+//!
+//! Sets of size `m` are indexed by their colexicographic rank,
+//! Σ<sub>i</sub> <sup>e<sub>i</sub></sup>C<sub>i+1</sub> for ascending elements `e`.
+
+use crate::capture::CaptureMut;
+use crate::comb::count_combinations;
+use crate::harville::scratch_col;
+use crate::matrix::Matrix;
+
+pub struct Alloc<'a> {
+    pub elements: CaptureMut<'a, Vec<usize>, [usize]>,
+    pub prefix_ranks: CaptureMut<'a, Vec<usize>, [usize]>,
+    pub suffix_ranks: CaptureMut<'a, Vec<usize>, [usize]>,
+    pub level: CaptureMut<'a, Vec<f64>, [f64]>,
+    pub next_level: CaptureMut<'a, Vec<f64>, [f64]>,
+    pub binomials: CaptureMut<'a, Matrix<usize>>,
+    pub summary: CaptureMut<'a, Matrix<f64>>,
+}
+
+impl Alloc<'_> {
+    pub fn new(runners: usize, ranks: usize) -> Self {
+        let level_len = Self::level_len(runners, ranks);
+        Self {
+            elements: vec![0; ranks].into(),
+            prefix_ranks: vec![0; ranks + 1].into(),
+            suffix_ranks: vec![0; ranks + 1].into(),
+            level: vec![0.0; level_len].into(),
+            next_level: vec![0.0; level_len].into(),
+            binomials: binomials(runners, ranks).into(),
+            summary: Matrix::allocate(ranks, runners).into(),
+        }
+    }
+
+    /// Withdraws the `runner` from the allocation without reallocating, to suit a probabilities
+    /// matrix from which the runner has been [scratched](crate::harville::scratch). The level
+    /// buffers and the binomials table are left oversized.
+    pub fn scratch(&mut self, runner: usize) {
+        let ranks = scratch_col(&mut self.summary, runner);
+        self.elements.truncate(ranks);
+    }
+
+    /// The number of states in the largest level, being the maximum of
+    /// <sup>n</sup>C<sub>m</sub> for `m` in `0..ranks`.
+    #[inline]
+    pub fn level_len(runners: usize, ranks: usize) -> usize {
+        (0..ranks)
+            .map(|m| count_combinations(runners, m))
+            .max()
+            .unwrap_or(1)
+    }
+}
+
+pub fn summary(probs: &Matrix<f64>) -> Matrix<f64> {
+    let runners = probs.cols();
+    let ranks = probs.rows();
+    let mut alloc = Alloc::new(runners, ranks);
+    summary_no_alloc(probs, &mut alloc);
+    alloc.summary.into_owned()
+}
+
+pub fn summary_no_alloc(probs: &Matrix<f64>, alloc: &mut Alloc) {
+    let Alloc {
+        elements,
+        prefix_ranks,
+        suffix_ranks,
+        level,
+        next_level,
+        binomials,
+        summary,
+    } = alloc;
+    debug_assert_eq!(
+        summary.rows(),
+        probs.rows(),
+        "number of rows in the probabilities matrix must equal to the number of rows in the summary matrix"
+    );
+    debug_assert_eq!(
+        summary.cols(),
+        probs.cols(),
+        "number of columns in the probabilities matrix must equal to the number of columns in the summary matrix"
+    );
+    debug_assert_eq!(
+        probs.rows(),
+        elements.len(),
+        "number of rows in the probabilities matrix must equal to the elements length"
+    );
+    debug_assert!(
+        binomials.rows() > probs.cols() && binomials.cols() > probs.rows() + 1,
+        "binomials table is too small for the probabilities matrix"
+    );
+    debug_assert!(
+        level.len() >= Alloc::level_len(probs.cols(), probs.rows())
+            && next_level.len() >= Alloc::level_len(probs.cols(), probs.rows()),
+        "level buffers are too small for the probabilities matrix"
+    );
+
+    let ranks = probs.rows();
+    let runners = probs.cols();
+    summary.fill(0.0);
+    if ranks == 0 {
+        return;
+    }
+
+    level[0] = 1.0;
+    for rank in 0..ranks {
+        let rank_probs = probs.row_slice(rank);
+        let has_next = rank + 1 < ranks;
+        let states = binomials[(runners, rank)];
+        if has_next {
+            next_level[..binomials[(runners, rank + 1)]].fill(0.0);
+        }
+
+        for state in 0..states {
+            let prob = level[state];
+            if prob == 0.0 {
+                continue;
+            }
+            let elements = &mut elements[..rank];
+            unrank_colex(binomials, runners, state, elements);
+
+            let mut remaining_prob = 1.;
+            for &element in elements.iter() {
+                remaining_prob -= rank_probs[element];
+            }
+
+            if has_next {
+                insertion_ranks(binomials, elements, prefix_ranks, suffix_ranks);
+            }
+
+            let mut position = 0;
+            for runner in 0..runners {
+                if position < rank && elements[position] == runner {
+                    position += 1;
+                    continue;
+                }
+                let runner_prob = rank_probs[runner];
+                if runner_prob == 0.0 {
+                    continue;
+                }
+                let placed_prob = prob * runner_prob / remaining_prob;
+                summary[(rank, runner)] += placed_prob;
+                if has_next {
+                    let next_state = prefix_ranks[position]
+                        + binomials[(runner, position + 1)]
+                        + suffix_ranks[position];
+                    next_level[next_state] += placed_prob;
+                }
+            }
+        }
+
+        if has_next {
+            std::mem::swap(level, next_level);
+        }
+    }
+}
+
+/// Tabulates <sup>n</sup>C<sub>r</sub> for `n` in `0..=runners` and `r` in `0..ranks + 2`, covering
+/// every coefficient needed to rank sets of up to `ranks` elements.
+pub(crate) fn binomials(runners: usize, ranks: usize) -> Matrix<usize> {
+    let mut binomials = Matrix::allocate(runners + 1, ranks + 2);
+    for n in 0..=runners {
+        for r in 0..ranks + 2 {
+            binomials[(n, r)] = count_combinations(n, r);
+        }
+    }
+    binomials
+}
+
+/// Prepares the colex ranks of the sets obtained by inserting an element `j` into the ascending
+/// `elements`. If `j` lands at position `t`, the rank of the new set is
+/// `prefix_ranks[t] + C(j, t + 1) + suffix_ranks[t]`: the unshifted ranks of the elements below
+/// `t`, the rank of `j` at `t`, and the shifted ranks of the elements from `t` onwards.
+#[inline]
+pub(crate) fn insertion_ranks(
+    binomials: &Matrix<usize>,
+    elements: &[usize],
+    prefix_ranks: &mut [usize],
+    suffix_ranks: &mut [usize],
+) {
+    prefix_ranks[0] = 0;
+    for (index, &element) in elements.iter().enumerate() {
+        prefix_ranks[index + 1] = prefix_ranks[index] + binomials[(element, index + 1)];
+    }
+    suffix_ranks[elements.len()] = 0;
+    for index in (0..elements.len()).rev() {
+        suffix_ranks[index] = suffix_ranks[index + 1] + binomials[(elements[index], index + 2)];
+    }
+}
+
+/// Decodes the colexicographic `rank` of a set into its ascending `elements`.
+#[inline]
+pub(crate) fn unrank_colex(binomials: &Matrix<usize>, runners: usize, rank: usize, elements: &mut [usize]) {
+    let mut residual = rank;
+    let mut candidate = runners;
+    for index in (0..elements.len()).rev() {
+        candidate -= 1;
+        while binomials[(candidate, index + 1)] > residual {
+            candidate -= 1;
+        }
+        elements[index] = candidate;
+        residual -= binomials[(candidate, index + 1)];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harville::classic;
+    use crate::probs::SliceExt;
+    use crate::testing::{assert_slice_f64_relative, dilated_probs, dilated_probs_with};
+    use assert_float_eq::assert_float_relative_eq;
+
+    fn assert_matches_classic(win_probs: &[f64], dilatives: &[f64]) {
+        let probs = dilated_probs_with(win_probs, dilatives);
+        let expected = classic::summary(&probs);
+        let actual = summary(&probs);
+        println!("summary:\n{}", actual.verbose());
+        assert_slice_f64_relative(expected.flatten(), actual.flatten(), 1e-9);
+    }
+
+    #[test]
+    fn unrank_colex_4c2() {
+        let alloc = Alloc::new(4, 3);
+        let mut elements = [0; 2];
+        let mut outputs = vec![];
+        for rank in 0..count_combinations(4, 2) {
+            unrank_colex(&alloc.binomials, 4, rank, &mut elements);
+            outputs.push(elements.to_vec());
+        }
+        assert_eq!(
+            vec![vec![0, 1], vec![0, 2], vec![1, 2], vec![0, 3], vec![1, 3], vec![2, 3]],
+            outputs
+        );
+    }
+
+    #[test]
+    fn subset_dp_summary_3x3_without_scratchings() {
+        let probs = dilated_probs(&[0.6, 0.3, 0.1], 3);
+        let summary = summary(&probs);
+        assert_slice_f64_relative(
+            &[
+                0.6,
+                0.3,
+                0.1,
+                0.32380952380952444,
+                0.48333333333333445,
+                0.19285714285714314,
+                0.07619047619047627,
+                0.216666666666667,
+                0.7071428571428587,
+            ],
+            summary.flatten(),
+            1e-9,
+        );
+    }
+
+    #[test]
+    fn subset_dp_summary_matches_classic() {
+        assert_matches_classic(&[0.4, 0.3, 0.2, 0.1], &[0.0; 4]);
+        assert_matches_classic(&[0.4, 0.3, 0.2, 0.1], &[0.0; 2]);
+        assert_matches_classic(&[0.4, 0.3, 0.2, 0.1], &[0.0, 0.1, 0.2, 0.3]);
+        assert_matches_classic(&[0.6, 0.3, 0.1, 0.0], &[0.0; 3]);
+        assert_matches_classic(&[0.25, 0.2, 0.15, 0.12, 0.1, 0.08, 0.06, 0.04], &[0.0, 0.1, 0.2, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn subset_dp_summary_16x16() {
+        let mut win_probs = (1..=16).map(|i| 1.0 / i as f64).collect::<Vec<_>>();
+        win_probs.normalise(1.0);
+        let probs = dilated_probs(&win_probs, 16);
+        let summary = summary(&probs);
+        assert_slice_f64_relative(&win_probs, &summary[0], 1e-9);
+        for row in summary.into_iter() {
+            assert_float_relative_eq!(1.0, row.sum(), 1e-9);
+        }
+        for col in 0..summary.cols() {
+            assert_float_relative_eq!(1.0, summary.col(col).sum::<f64>(), 1e-9);
+        }
+    }
+
+    #[test]
+    fn subset_dp_summary_reuse_alloc() {
+        let probs = dilated_probs(&[0.4, 0.3, 0.2, 0.1], 3);
+        let mut alloc = Alloc::new(4, 3);
+        summary_no_alloc(&probs, &mut alloc);
+        let first = (*alloc.summary).clone();
+        summary_no_alloc(&probs, &mut alloc);
+        assert_eq!(first, *alloc.summary);
+    }
+}
@@ -1,14 +1,18 @@
 pub mod capture;
 pub mod comb;
+pub mod coupled;
 pub mod dilative;
 pub mod dis_cons;
 pub mod each_way;
+pub mod exotics;
 pub mod harville;
 pub mod logic;
 pub mod market;
+pub mod matchup;
 pub mod matrix;
 pub mod opt;
 pub mod place;
+pub mod position;
 pub mod probs;
 pub mod random;
 pub mod stream;
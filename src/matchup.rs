@@ -0,0 +1,235 @@
+//! Matchups — head-to-head and N-way "best finisher" markets within a field.
+//!
+//! The leader of a group is the member finishing ahead of all other members. Member `g` leads the
+//! group from rank `m` if the runners placed in ranks `0..m` are all outside the group, and `g`
+//! takes rank `m`. Under the Harville model over a (possibly dilated) probabilities matrix, the
+//! probability of the first event depends only on the _set_ of non-group runners placed, not on
+//! their order; the leader probabilities are therefore accumulated by dynamic programming over
+//! subsets of non-group runners, level-by-level, as in [`subset_dp`](crate::harville::subset_dp).
+//!
+//! Without dilation, the Harville model has Luce's independence of irrelevant alternatives
+//! property, and the head-to-head probability reduces to the ratio of the two win probabilities.
+//! Dilation breaks this property, as runners fare differently in the lower ranks.
+//!
+//! The computation is exact if the probabilities matrix has more rows than there are runners
+//! outside the group. Otherwise, the mass in which no member finishes within the rows of the
+//! matrix is split among the members in proportion to their probabilities in the last row.
+//! As the cost grows with <sup>m</sup>C<sub>0</sub> + … + <sup>m</sup>C<sub>k-1</sub> for `m`
+//! non-group runners and `k` rows, large fields are best priced over a truncated matrix.
+
+use crate::harville::subset_dp;
+use crate::harville::subset_dp::{insertion_ranks, unrank_colex};
+use crate::market::{Market, Overround, PriceBounds};
+use crate::matrix::Matrix;
+use crate::probs::SliceExt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leaders {
+    /// Probability of each member (column) leading the group from each rank (row).
+    pub by_rank: Matrix<f64>,
+
+    /// Probability of each member leading the group from beyond the ranks in the probabilities
+    /// matrix.
+    pub unranked: Vec<f64>,
+}
+
+impl Leaders {
+    /// Probability of each member leading the group, irrespective of rank.
+    pub fn probs(&self) -> Vec<f64> {
+        self.unranked
+            .iter()
+            .enumerate()
+            .map(|(member, unranked)| self.by_rank.col(member).sum::<f64>() + unranked)
+            .collect()
+    }
+}
+
+/// Computes the probability of each member of the `group` leading the group from each rank.
+/// A group may comprise a single runner, in which case its leader probabilities are simply the
+/// probabilities of the runner finishing in each rank.
+pub fn leaders(probs: &Matrix<f64>, group: &[usize]) -> Leaders {
+    validate_group(probs, group);
+    let ranks = probs.rows();
+    let others = (0..probs.cols())
+        .filter(|runner| !group.contains(runner))
+        .collect::<Vec<_>>();
+    let levels = ranks.min(others.len() + 1);
+
+    let binomials = subset_dp::binomials(others.len(), levels);
+    let level_len = subset_dp::Alloc::level_len(others.len(), levels);
+    let mut level = vec![0.0; level_len];
+    let mut next_level = vec![0.0; level_len];
+    let mut elements = vec![0; levels];
+    let mut prefix_ranks = vec![0; levels + 1];
+    let mut suffix_ranks = vec![0; levels + 1];
+    let mut by_rank = Matrix::allocate(ranks, group.len());
+
+    level[0] = 1.0;
+    for rank in 0..levels {
+        let rank_probs = probs.row_slice(rank);
+        let has_next = rank + 1 < levels;
+        if has_next {
+            next_level[..binomials[(others.len(), rank + 1)]].fill(0.0);
+        }
+
+        for (state, &prob) in level[..binomials[(others.len(), rank)]].iter().enumerate() {
+            if prob == 0.0 {
+                continue;
+            }
+            let elements = &mut elements[..rank];
+            unrank_colex(&binomials, others.len(), state, elements);
+
+            let mut remaining_prob = 1.0;
+            for &element in elements.iter() {
+                remaining_prob -= rank_probs[others[element]];
+            }
+
+            for (member, &runner) in group.iter().enumerate() {
+                by_rank[(rank, member)] += prob * rank_probs[runner] / remaining_prob;
+            }
+
+            if has_next {
+                insertion_ranks(&binomials, elements, &mut prefix_ranks, &mut suffix_ranks);
+                let mut position = 0;
+                for (element, &runner) in others.iter().enumerate() {
+                    if position < rank && elements[position] == element {
+                        position += 1;
+                        continue;
+                    }
+                    let runner_prob = rank_probs[runner];
+                    if runner_prob == 0.0 {
+                        continue;
+                    }
+                    let next_state =
+                        prefix_ranks[position] + binomials[(element, position + 1)] + suffix_ranks[position];
+                    next_level[next_state] += prob * runner_prob / remaining_prob;
+                }
+            }
+        }
+
+        if has_next {
+            std::mem::swap(&mut level, &mut next_level);
+        }
+    }
+
+    let residual = (1.0 - by_rank.flatten().sum()).max(0.0);
+    let mut unranked = group
+        .iter()
+        .map(|&runner| probs[(ranks - 1, runner)])
+        .collect::<Vec<_>>();
+    if unranked.sum() == 0.0 {
+        unranked.fill(1.0);
+    }
+    unranked.normalise(residual);
+
+    Leaders { by_rank, unranked }
+}
+
+/// Probability of each member of the `group` finishing ahead of all other members.
+#[inline]
+pub fn best_of(probs: &Matrix<f64>, group: &[usize]) -> Vec<f64> {
+    leaders(probs, group).probs()
+}
+
+/// Probability of runner `a` finishing ahead of runner `b`.
+#[inline]
+pub fn head_to_head(probs: &Matrix<f64>, a: usize, b: usize) -> f64 {
+    best_of(probs, &[a, b])[0]
+}
+
+/// Frames a two-way or N-way matchup market over the `group`, with the desired `overround`.
+pub fn frame(probs: &Matrix<f64>, group: &[usize], overround: &Overround, bounds: &PriceBounds) -> Market {
+    assert!(group.len() >= 2, "a matchup requires at least two runners");
+    Market::frame(overround, best_of(probs, group), bounds)
+}
+
+#[inline]
+fn validate_group(probs: &Matrix<f64>, group: &[usize]) {
+    assert!(!group.is_empty(), "group cannot be empty");
+    assert!(probs.rows() > 0, "probabilities matrix must have at least one row");
+    for (index, &runner) in group.iter().enumerate() {
+        assert!(
+            runner < probs.cols(),
+            "runner {runner} is out of bounds for {} runners",
+            probs.cols()
+        );
+        assert!(
+            !group[..index].contains(&runner),
+            "runner {runner} appears more than once in the group"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comb::permuter::Permuter;
+    use crate::harville::harville;
+    use crate::stream::generator::Generator;
+    use crate::testing::{assert_slice_f64_relative, dilated_probs, dilated_probs_with};
+    use assert_float_eq::*;
+
+    const BOUNDS: PriceBounds = 1.04..=10_001.0;
+
+    fn brute_force_best_of(probs: &Matrix<f64>, group: &[usize]) -> Vec<f64> {
+        let runners = probs.cols();
+        let mut best = vec![0.0; group.len()];
+        let mut permuter = Permuter::new(runners, runners);
+        loop {
+            let podium = permuter.read();
+            let leader = podium.iter().find(|runner| group.contains(runner)).unwrap();
+            let member = group.iter().position(|runner| runner == leader).unwrap();
+            best[member] += harville(probs, podium);
+            if !permuter.advance() {
+                break;
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn head_to_head_without_dilation() {
+        const WIN_PROBS: [f64; 5] = [0.3, 0.25, 0.2, 0.15, 0.1];
+        let probs = dilated_probs(&WIN_PROBS, 5);
+        assert_float_relative_eq!(0.3 / 0.4, head_to_head(&probs, 0, 4));
+        assert_float_relative_eq!(0.15 / 0.4, head_to_head(&probs, 3, 1));
+    }
+
+    #[test]
+    fn best_of_with_dilation() {
+        const WIN_PROBS: [f64; 6] = [0.3, 0.25, 0.2, 0.12, 0.08, 0.05];
+        let probs = dilated_probs_with(&WIN_PROBS, &[0.0, 0.1, 0.2, 0.3, 0.4, 0.5]);
+        for group in [vec![0, 5], vec![4, 1, 2], vec![5, 3, 1, 0]] {
+            let expected = brute_force_best_of(&probs, &group);
+            let leaders = leaders(&probs, &group);
+            assert_slice_f64_relative(&expected, &leaders.probs(), 1e-9);
+            assert!(leaders.unranked.iter().all(|&prob| prob.abs() < 1e-12));
+        }
+        assert_ne!(0.3 / 0.35, head_to_head(&probs, 0, 5));
+    }
+
+    #[test]
+    fn best_of_truncated() {
+        const WIN_PROBS: [f64; 6] = [0.3, 0.25, 0.2, 0.12, 0.08, 0.05];
+        let probs = dilated_probs(&WIN_PROBS, 2);
+        let leaders = leaders(&probs, &[3, 4, 5]);
+        assert_eq!(2, leaders.by_rank.rows());
+        assert!(leaders.unranked.sum() > 0.0);
+        assert_slice_f64_relative(&[0.12 / 0.25, 0.08 / 0.25, 0.05 / 0.25], &leaders.probs(), 1e-9);
+    }
+
+    #[test]
+    fn frame_head_to_head() {
+        let probs = dilated_probs(&[0.5, 0.3, 0.2], 3);
+        let market = frame(&probs, &[1, 2], &Overround::fair(), &BOUNDS);
+        assert_slice_f64_relative(&[0.6, 0.4], &market.probs, 1e-9);
+        assert_float_relative_eq!(1.0, market.offered_booksum());
+    }
+
+    #[test]
+    #[should_panic(expected = "runner 1 appears more than once in the group")]
+    fn duplicate_in_group() {
+        let probs = dilated_probs(&[0.5, 0.3, 0.2], 3);
+        best_of(&probs, &[1, 2, 1]);
+    }
+}
@@ -91,6 +91,47 @@ impl<T> Matrix<T> {
         }
     }
 
+    /// Changes the dimensions of the matrix to `rows` x `cols`, reallocating only if the underlying
+    /// storage lacks the capacity. The contents of the reshaped matrix are unspecified.
+    #[inline]
+    pub fn reshape(&mut self, rows: usize, cols: usize) where T: Default {
+        let (len, overflow) = rows.overflowing_mul(cols);
+        assert!(
+            !overflow,
+            "reshaping to a {rows}x{cols} matrix failed due to overflow"
+        );
+        self.data.resize_with(len, T::default);
+        self.rows = rows;
+        self.cols = cols;
+    }
+
+    /// Removes the given column in place, shifting the subsequent columns to the left. The
+    /// underlying storage is retained.
+    pub fn remove_col(&mut self, col: usize) {
+        debug_assert!(self.validate_col_index(col));
+        let cols = self.cols;
+        let mut index = 0;
+        self.data.retain(|_| {
+            let retain = index % cols != col;
+            index += 1;
+            retain
+        });
+        self.cols -= 1;
+    }
+
+    /// Retains only the first `rows` rows. The underlying storage is retained.
+    #[inline]
+    pub fn truncate_rows(&mut self, rows: usize) {
+        assert!(
+            rows <= self.rows,
+            "cannot truncate a {}x{} matrix to {rows} rows",
+            self.rows,
+            self.cols
+        );
+        self.data.truncate(rows * self.cols);
+        self.rows = rows;
+    }
+
     pub fn verbose(&self) -> VerboseFormat<T> {
         VerboseFormat { referent: self }
     }
@@ -144,6 +185,13 @@ impl<T> Matrix<T> {
     }
 }
 
+impl<T> Default for Matrix<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
 impl<T> Display for Matrix<T> where T: Debug {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         for row in 0..self.rows {
@@ -387,6 +435,39 @@ mod tests {
         assert_eq!(None, iter.next());
     }
 
+    #[test]
+    fn remove_col() {
+        let mut matrix = Matrix::allocate(2, 3);
+        populate_with_test_data(&mut matrix);
+        matrix.remove_col(1);
+        assert_eq!(2, matrix.cols());
+        assert_eq!(&[0., 20.], &matrix[0]);
+        assert_eq!(&[30., 50.], &matrix[1]);
+        matrix.remove_col(1);
+        assert_eq!(&[0., 30.], matrix.flatten());
+    }
+
+    #[test]
+    fn truncate_rows() {
+        let mut matrix = Matrix::allocate(3, 2);
+        populate_with_test_data(&mut matrix);
+        matrix.truncate_rows(1);
+        assert_eq!(1, matrix.rows());
+        assert_eq!(&[0., 10.], matrix.flatten());
+    }
+
+    #[test]
+    fn reshape() {
+        let mut matrix = Matrix::<f64>::allocate(3, 4);
+        let capacity = matrix.data.capacity();
+        matrix.reshape(2, 5);
+        assert_eq!((2, 5), (matrix.rows(), matrix.cols()));
+        assert_eq!(10, matrix.flatten().len());
+        assert_eq!(capacity, matrix.data.capacity());
+        matrix.reshape(4, 4);
+        assert_eq!(16, matrix.flatten().len());
+    }
+
     #[test]
     fn read_col() {
         let mut matrix = Matrix::allocate(3, 2);
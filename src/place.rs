@@ -1,12 +1,15 @@
 use crate::capture::Capture;
 use crate::dilative::DilatedProbs;
-use crate::harville::{classic, harville_est, mass_samp, rand_samp, sticky_samp};
+use crate::harville::{balance, classic, harville_est, harville_est_no_alloc, mass_samp, rand_samp, sticky_samp, subset_dp};
 use crate::market::{Market, Overround, OverroundMethod, PriceBounds};
 use crate::matrix::Matrix;
 use crate::opt::{univariate_descent, UnivariateDescentConfig};
+use crate::position;
 use crate::probs::SliceExt;
 use std::ops::Div;
 
+pub mod workspace;
+
 /// Converts win odds to place using naive (E/W) odds-ratio.
 pub fn win_to_place_or(win_odds: &[f64], d: usize) -> Vec<f64> {
     let d = d as f64;
@@ -52,13 +55,7 @@ pub fn win_to_place_harville(win_probs: &[f64], k: usize) -> Vec<f64> {
             .with_podium_places(k),
     );
     let rank_probs = classic::summary(&win_probs);
-    (0..rank_probs.cols())
-        .map(|col| {
-            (0..rank_probs.rows())
-                .map(|row| rank_probs[(row, col)])
-                .sum()
-        })
-        .collect()
+    position::top(&rank_probs)
 }
 
 /// Produces place probability estimates for `k` placings using an alternative Harville estimation method.
@@ -83,7 +80,90 @@ pub fn win_to_place_upscaled(win_probs: &[f64], k: usize, fit_rank_idx: usize) -
                 .with_podium_places(fit_rank_idx + 1),
         )
     );
-    let harville = &harville[fit_rank_idx];
+    let lambda = fit_lambda(win_probs, fit_rank_idx, &harville[fit_rank_idx]);
+    upscale(win_probs, k, |_| lambda)
+}
+
+/// Produces place probability estimates for `k` placings using the upscaled estimator, taking
+/// a separately fitted lambda for each rank from the given `curve`.
+pub fn win_to_place_upscaled_per_rank(win_probs: &[f64], k: usize, curve: &LambdaCurve) -> Vec<f64> {
+    upscale(win_probs, k, |rank_idx| curve.lambda(rank_idx))
+}
+
+/// Lambda of the upscaled estimator as a function of the rank index. Lambdas are fitted
+/// individually against exact Harville for the leading ranks, and extrapolated linearly (by
+/// least squares over the fitted ranks) beyond them.
+///
+/// The curve depends mostly on the field size, so it may be fitted once and reused across
+/// similar fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LambdaCurve {
+    /// Fitted lambdas for rank indices 1 to `lambdas.len()` (inclusive).
+    pub lambdas: Vec<f64>,
+    pub slope: f64,
+    pub intercept: f64,
+}
+
+impl LambdaCurve {
+    /// Fits a lambda for each of the rank indices 1 to `fit_ranks` (inclusive).
+    pub fn fit(win_probs: &[f64], fit_ranks: usize) -> Self {
+        assert!(fit_ranks > 0, "at least one rank must be fitted");
+        assert!(
+            fit_ranks < win_probs.len(),
+            "number of fitted ranks ({fit_ranks}) must be less than the number of runners ({})",
+            win_probs.len()
+        );
+        let harville = subset_dp::summary(&Matrix::from(
+            DilatedProbs::default()
+                .with_win_probs(Capture::Borrowed(win_probs))
+                .with_podium_places(fit_ranks + 1),
+        ));
+        let lambdas = (1..=fit_ranks)
+            .map(|rank_idx| fit_lambda(win_probs, rank_idx, &harville[rank_idx]))
+            .collect::<Vec<_>>();
+
+        let (slope, intercept) = if lambdas.len() == 1 {
+            (0.0, lambdas[0])
+        } else {
+            let n = lambdas.len() as f64;
+            let mean_rank = (1..=fit_ranks).sum::<usize>() as f64 / n;
+            let mean_lambda = lambdas.sum() / n;
+            let (mut covariance, mut variance) = (0.0, 0.0);
+            for (rank_idx, lambda) in (1..=fit_ranks).zip(lambdas.iter()) {
+                let rank_dev = rank_idx as f64 - mean_rank;
+                covariance += rank_dev * (lambda - mean_lambda);
+                variance += rank_dev * rank_dev;
+            }
+            let slope = covariance / variance;
+            (slope, mean_lambda - slope * mean_rank)
+        };
+        log::trace!("lambdas: {lambdas:?}, slope: {slope}, intercept: {intercept}");
+        Self {
+            lambdas,
+            slope,
+            intercept,
+        }
+    }
+
+    /// The lambda for the given rank index (1 onwards).
+    #[inline]
+    pub fn lambda(&self, rank_idx: usize) -> f64 {
+        debug_assert!(rank_idx > 0, "the win rank is not estimated");
+        match self.lambdas.get(rank_idx - 1) {
+            Some(&lambda) => lambda,
+            None => self.intercept + self.slope * rank_idx as f64,
+        }
+    }
+}
+
+/// Fits the lambda of [`harville_est`] for the given rank index to the `target` rank probabilities.
+fn fit_lambda(win_probs: &[f64], rank_idx: usize, target: &[f64]) -> f64 {
+    fit_lambda_no_alloc(win_probs, rank_idx, target, &mut vec![0.0; win_probs.len()])
+}
+
+/// Equivalent of [`fit_lambda`], estimating into `est`, whose length must equal that of
+/// `win_probs`.
+pub(crate) fn fit_lambda_no_alloc(win_probs: &[f64], rank_idx: usize, target: &[f64], est: &mut [f64]) -> f64 {
     let outcome = univariate_descent(
         &UnivariateDescentConfig {
             init_value: 1.1,
@@ -93,33 +173,43 @@ pub fn win_to_place_upscaled(win_probs: &[f64], k: usize, fit_rank_idx: usize) -
             acceptable_residual: 0.0001,
         },
         |value| {
-            let est = harville_est(win_probs, fit_rank_idx, value);
+            harville_est_no_alloc(win_probs, rank_idx, value, est);
             let sq_err = est
                 .iter()
-                .zip(harville.iter())
+                .zip(target.iter())
                 .map(|(est, harv)| (est - harv).powi(2))
                 .sum::<f64>();
             sq_err.div(est.len() as f64).sqrt()
         },
     );
     log::trace!("opt. outcome={outcome:?}");
-    let all_rank_probs = (1..k)
-        .map(|rank_idx| harville_est(win_probs, rank_idx, outcome.optimal_value))
-        .collect::<Vec<_>>();
-    let mut place_probs = win_probs
-        .iter()
-        .enumerate()
-        .map(|(index, win_prob)| {
-            win_prob
-                + all_rank_probs
-                .iter()
-                .map(|rank_probs| rank_probs[index])
-                .sum::<f64>()
-        })
-        .collect::<Vec<_>>();
+    outcome.optimal_value
+}
+
+fn upscale(win_probs: &[f64], k: usize, lambda: impl Fn(usize) -> f64) -> Vec<f64> {
+    let mut place_probs = vec![0.0; win_probs.len()];
+    upscale_no_alloc(win_probs, k, lambda, &mut vec![0.0; win_probs.len()], &mut place_probs);
+    place_probs
+}
+
+/// Equivalent of [`upscale`], estimating each rank into `rank_probs` and accumulating into
+/// `place_probs`, the lengths of both must equal that of `win_probs`.
+pub(crate) fn upscale_no_alloc(
+    win_probs: &[f64],
+    k: usize,
+    lambda: impl Fn(usize) -> f64,
+    rank_probs: &mut [f64],
+    place_probs: &mut [f64],
+) {
+    place_probs.copy_from_slice(win_probs);
+    for rank_idx in 1..k {
+        harville_est_no_alloc(win_probs, rank_idx, lambda(rank_idx), rank_probs);
+        for (place_prob, rank_prob) in place_probs.iter_mut().zip(rank_probs.iter()) {
+            *place_prob += rank_prob;
+        }
+    }
     log::trace!("redistributing {place_probs:?}");
     place_probs.redistribute();
-    place_probs
 }
 
 /// Produces place probability estimates for `k` placings using the poly-Harville method.
@@ -130,13 +220,7 @@ pub fn win_to_place_rand_samp(win_probs: &[f64], k: usize, degree: usize) -> Vec
             .with_podium_places(k),
     );
     let rank_probs = rand_samp::summary(&win_probs, degree);
-    (0..rank_probs.cols())
-        .map(|col| {
-            (0..rank_probs.rows())
-                .map(|row| rank_probs[(row, col)])
-                .sum()
-        })
-        .collect()
+    position::top(&rank_probs)
 }
 
 pub fn win_to_place_mass_samp(win_probs: &[f64], k: usize, degree: usize) -> Vec<f64> {
@@ -146,13 +230,7 @@ pub fn win_to_place_mass_samp(win_probs: &[f64], k: usize, degree: usize) -> Vec
             .with_podium_places(k),
     );
     let rank_probs = mass_samp::summary(&win_probs, degree);
-    let mut place_probs = (0..rank_probs.cols())
-        .map(|col| {
-            (0..rank_probs.rows())
-                .map(|row| rank_probs[(row, col)])
-                .sum()
-        })
-        .collect::<Vec<_>>();
+    let mut place_probs = position::top(&rank_probs);
     place_probs.redistribute();
     place_probs
 }
@@ -164,13 +242,103 @@ pub fn win_to_place_sticky_samp(win_probs: &[f64], k: usize, degree: usize) -> V
             .with_podium_places(k),
     );
     let rank_probs = sticky_samp::summary(&win_probs, degree);
-    let mut place_probs = (0..rank_probs.cols())
-        .map(|col| {
-            (0..rank_probs.rows())
-                .map(|row| rank_probs[(row, col)])
-                .sum()
-        })
-        .collect::<Vec<_>>();
+    let mut place_probs = position::top(&rank_probs);
     place_probs.redistribute();
     place_probs
 }
+
+/// Equivalent of [`win_to_place_mass_samp`], [balancing](balance) the sampled summary so that
+/// no place probability exceeds 1 before summing its ranks.
+pub fn win_to_place_mass_samp_balanced(win_probs: &[f64], k: usize, degree: usize, config: &balance::Config) -> Vec<f64> {
+    let win_probs = Matrix::from(
+        DilatedProbs::default()
+            .with_win_probs(Capture::Borrowed(win_probs))
+            .with_podium_places(k),
+    );
+    let rank_probs = mass_samp::summary(&win_probs, degree);
+    balanced_place_probs(rank_probs, config)
+}
+
+/// Equivalent of [`win_to_place_sticky_samp`], [balancing](balance) the sampled summary so that
+/// no place probability exceeds 1 before summing its ranks.
+pub fn win_to_place_sticky_samp_balanced(win_probs: &[f64], k: usize, degree: usize, config: &balance::Config) -> Vec<f64> {
+    let win_probs = Matrix::from(
+        DilatedProbs::default()
+            .with_win_probs(Capture::Borrowed(win_probs))
+            .with_podium_places(k),
+    );
+    let rank_probs = sticky_samp::summary(&win_probs, degree);
+    balanced_place_probs(rank_probs, config)
+}
+
+/// Balances the `rank_probs`, falling back to redistributing the place probabilities should
+/// balancing not converge.
+fn balanced_place_probs(mut rank_probs: Matrix<f64>, config: &balance::Config) -> Vec<f64> {
+    let outcome = balance::balance(&mut rank_probs, config);
+    let mut place_probs = position::top(&rank_probs);
+    if !outcome.converged(config) {
+        log::trace!("balancing did not converge: {outcome:?}");
+        place_probs.redistribute();
+    }
+    place_probs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_float_eq::*;
+
+    const WIN_PROBS: [f64; 8] = [0.3, 0.2, 0.15, 0.12, 0.1, 0.07, 0.04, 0.02];
+
+    #[test]
+    fn lambda_curve_extrapolation() {
+        let curve = LambdaCurve::fit(&WIN_PROBS, 3);
+        assert_eq!(3, curve.lambdas.len());
+        for rank_idx in 1..=3 {
+            assert_eq!(curve.lambdas[rank_idx - 1], curve.lambda(rank_idx));
+        }
+        assert_float_relative_eq!(curve.intercept + 5.0 * curve.slope, curve.lambda(5));
+
+        let curve = LambdaCurve::fit(&WIN_PROBS, 1);
+        assert_eq!(0.0, curve.slope);
+        assert_eq!(curve.lambdas[0], curve.lambda(4));
+    }
+
+    #[test]
+    fn upscaled_per_rank_vs_harville() {
+        let harville = win_to_place_harville(&WIN_PROBS, 3);
+        let curve = LambdaCurve::fit(&WIN_PROBS, 2);
+        let per_rank = win_to_place_upscaled_per_rank(&WIN_PROBS, 3, &curve);
+        assert_float_relative_eq!(3.0, per_rank.sum());
+        let single = win_to_place_upscaled(&WIN_PROBS, 3, 1);
+        let rmse = |est: &[f64]| {
+            est.iter()
+                .zip(harville.iter())
+                .map(|(est, harv)| (est - harv).powi(2))
+                .sum::<f64>()
+                .sqrt()
+        };
+        println!("per_rank: {per_rank:?}, single: {single:?}, harville: {harville:?}");
+        assert!(rmse(&per_rank) <= rmse(&single) + 1e-6);
+        assert!(rmse(&per_rank) < 0.02);
+    }
+
+    #[test]
+    fn balanced_samp_vs_harville() {
+        let config = balance::Config::default();
+        for k in [2, 3, 8] {
+            let harville = win_to_place_harville(&WIN_PROBS, k);
+            for place_probs in [
+                win_to_place_mass_samp_balanced(&WIN_PROBS, k, 1, &config),
+                win_to_place_sticky_samp_balanced(&WIN_PROBS, k, 1, &config),
+            ] {
+                println!("k: {k}, balanced: {place_probs:?}, harville: {harville:?}");
+                assert_float_relative_eq!(k as f64, place_probs.sum(), 1e-6);
+                for (place_prob, harville) in place_probs.iter().zip(&harville) {
+                    assert!(*place_prob <= 1.0 + 1e-6);
+                    assert!((place_prob - harville).abs() < 0.1);
+                }
+            }
+        }
+    }
+}
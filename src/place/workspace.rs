@@ -0,0 +1,334 @@
+//! A reusable workspace for place pricing.
+//!
+//! Each of the `win_to_place_*` functions in [`place`](crate::place) builds a fresh probabilities
+//! matrix and a fresh summary allocation. A [`Workspace`] instead owns the buffers for all
+//! estimators, grows them to suit the largest field and `k` seen, and hands out borrowed views
+//! of the buffers shrunk to the size of each call. Once warmed up, pricing is allocation-free.
+//!
+//! The workspace mirrors the Harville, sampled and upscaled estimators. The odds-ratio estimators
+//! and [`win_to_place_est`](crate::place::win_to_place_est) are not mirrored.
+
+use crate::capture::{Capture, CaptureMut};
+use crate::comb::bitmap::Bitmap;
+use crate::comb::sticky_permuter;
+use crate::dilative::DilatedProbs;
+use crate::harville::{classic, mass_samp, rand_samp, sticky_samp};
+use crate::matrix::Matrix;
+use crate::place::{fit_lambda_no_alloc, upscale_no_alloc, LambdaCurve};
+use crate::position;
+use crate::probs::SliceExt;
+use tinyrand::StdRand;
+
+#[derive(Default)]
+pub struct Workspace {
+    runners: usize,
+    ranks: usize,
+    dilatives: Vec<f64>,
+    probs: Matrix<f64>,
+    remaining: Matrix<f64>,
+    summary: Matrix<f64>,
+    podium: Vec<usize>,
+    bitmap: Bitmap,
+    tree: Vec<usize>,
+    sorted_runners: Vec<usize>,
+    sans_self_runners: Vec<usize>,
+    sans_self_podium: Vec<usize>,
+    sticky_alloc: Option<sticky_permuter::Alloc<'static>>,
+    rand: StdRand,
+    rank_probs: Vec<f64>,
+    place_probs: Vec<f64>,
+}
+
+impl Workspace {
+    /// Creates a workspace with buffers sized for the given number of `runners` and `ranks`.
+    pub fn new(runners: usize, ranks: usize) -> Self {
+        let mut workspace = Self::default();
+        workspace.reserve(runners, ranks);
+        workspace
+    }
+
+    /// Grows the buffers, if necessary, to suit the given number of `runners` and `ranks`.
+    pub fn reserve(&mut self, runners: usize, ranks: usize) {
+        assert!(
+            ranks <= runners,
+            "number of ranks ({ranks}) cannot exceed the number of runners ({runners})"
+        );
+        if runners > self.runners {
+            self.tree.resize(runners, 0);
+            self.sorted_runners.resize(runners, 0);
+            self.sans_self_runners.resize(runners, 0);
+            self.rank_probs.resize(runners, 0.0);
+            self.place_probs.resize(runners, 0.0);
+            self.runners = runners;
+        }
+        if ranks > self.ranks {
+            self.dilatives.resize(ranks, 0.0);
+            self.podium.resize(ranks, 0);
+            self.sans_self_podium.resize(ranks, 0);
+            self.sticky_alloc = Some(sticky_permuter::Alloc::new(ranks.saturating_sub(1)));
+            self.ranks = ranks;
+        }
+    }
+
+    /// The largest number of runners supported without reallocating.
+    #[inline]
+    pub fn runners(&self) -> usize {
+        self.runners
+    }
+
+    /// The largest number of ranks supported without reallocating.
+    #[inline]
+    pub fn ranks(&self) -> usize {
+        self.ranks
+    }
+
+    /// Populates the probabilities matrix for `k` podium places, returning it.
+    pub fn probs(&mut self, win_probs: &[f64], k: usize) -> &Matrix<f64> {
+        self.populate(win_probs, k);
+        &self.probs
+    }
+
+    /// Borrowed [`classic`] allocation for the given number of `runners` and `ranks`.
+    pub fn classic_alloc(&mut self, runners: usize, ranks: usize) -> classic::Alloc<'_> {
+        self.reserve(runners, ranks);
+        self.remaining.reshape(ranks, ranks);
+        self.summary.reshape(ranks, runners);
+        self.bitmap.resize(runners);
+        classic::Alloc {
+            podium: CaptureMut::Borrowed(&mut self.podium[..ranks]),
+            bitmap: CaptureMut::Borrowed(&mut self.bitmap),
+            remaining: CaptureMut::Borrowed(&mut self.remaining),
+            summary: CaptureMut::Borrowed(&mut self.summary),
+        }
+    }
+
+    /// Borrowed [`mass_samp`] allocation for the given number of `runners` and `ranks`.
+    pub fn mass_samp_alloc(&mut self, runners: usize, ranks: usize) -> mass_samp::Alloc<'_> {
+        self.reserve(runners, ranks);
+        self.summary.reshape(ranks, runners);
+        self.bitmap.resize(runners - 1);
+        mass_samp::Alloc {
+            podium: CaptureMut::Borrowed(&mut self.podium[..ranks]),
+            bitmap: CaptureMut::Borrowed(&mut self.bitmap),
+            sorted_runners: CaptureMut::Borrowed(&mut self.sorted_runners[..runners]),
+            sans_self_runners: CaptureMut::Borrowed(&mut self.sans_self_runners[..runners - 1]),
+            sans_self_podium: CaptureMut::Borrowed(&mut self.sans_self_podium[..ranks - 1]),
+            summary: CaptureMut::Borrowed(&mut self.summary),
+        }
+    }
+
+    /// Borrowed [`sticky_samp`] allocation for the given number of `runners` and `ranks`.
+    pub fn sticky_samp_alloc(&mut self, runners: usize, ranks: usize) -> sticky_samp::Alloc<'_> {
+        self.reserve(runners, ranks);
+        self.summary.reshape(ranks, runners);
+        sticky_samp::Alloc {
+            podium: CaptureMut::Borrowed(&mut self.podium[..ranks]),
+            sorted_runners: CaptureMut::Borrowed(&mut self.sorted_runners[..runners]),
+            sans_self_runners: CaptureMut::Borrowed(&mut self.sans_self_runners[..runners - 1]),
+            sticky_alloc: self.sticky_alloc.as_mut().unwrap().shrink(ranks - 1),
+            summary: CaptureMut::Borrowed(&mut self.summary),
+        }
+    }
+
+    /// Borrowed [`rand_samp`] allocation for the given number of `runners` and `ranks`.
+    pub fn rand_samp_alloc(&mut self, runners: usize, ranks: usize) -> rand_samp::Alloc<'_, StdRand> {
+        self.reserve(runners, ranks);
+        self.summary.reshape(ranks, runners);
+        rand_samp::Alloc {
+            podium: CaptureMut::Borrowed(&mut self.podium[..ranks]),
+            tree: CaptureMut::Borrowed(&mut self.tree[..runners]),
+            rand: CaptureMut::Borrowed(&mut self.rand),
+            summary: CaptureMut::Borrowed(&mut self.summary),
+        }
+    }
+
+    /// Equivalent of [`win_to_place_harville`](crate::place::win_to_place_harville).
+    pub fn win_to_place_harville(&mut self, win_probs: &[f64], k: usize) -> &[f64] {
+        self.populate(win_probs, k);
+        let probs = std::mem::take(&mut self.probs);
+        classic::summary_no_alloc(&probs, &mut self.classic_alloc(probs.cols(), probs.rows()));
+        self.probs = probs;
+        self.place_probs(win_probs.len(), false)
+    }
+
+    /// Equivalent of [`win_to_place_mass_samp`](crate::place::win_to_place_mass_samp).
+    pub fn win_to_place_mass_samp(&mut self, win_probs: &[f64], k: usize, degree: usize) -> &[f64] {
+        self.populate(win_probs, k);
+        let probs = std::mem::take(&mut self.probs);
+        mass_samp::summary_no_alloc(&probs, degree, &mut self.mass_samp_alloc(probs.cols(), probs.rows()));
+        self.probs = probs;
+        self.place_probs(win_probs.len(), true)
+    }
+
+    /// Equivalent of [`win_to_place_sticky_samp`](crate::place::win_to_place_sticky_samp).
+    pub fn win_to_place_sticky_samp(&mut self, win_probs: &[f64], k: usize, degree: usize) -> &[f64] {
+        self.populate(win_probs, k);
+        let probs = std::mem::take(&mut self.probs);
+        sticky_samp::summary_no_alloc(&probs, degree, &mut self.sticky_samp_alloc(probs.cols(), probs.rows()));
+        self.probs = probs;
+        self.place_probs(win_probs.len(), true)
+    }
+
+    /// Equivalent of [`win_to_place_rand_samp`](crate::place::win_to_place_rand_samp), drawing on
+    /// the workspace's random number generator.
+    pub fn win_to_place_rand_samp(&mut self, win_probs: &[f64], k: usize, degree: usize) -> &[f64] {
+        self.populate(win_probs, k);
+        let probs = std::mem::take(&mut self.probs);
+        rand_samp::summary_no_alloc(&probs, degree, &mut self.rand_samp_alloc(probs.cols(), probs.rows()));
+        self.probs = probs;
+        self.place_probs(win_probs.len(), false)
+    }
+
+    /// Equivalent of [`win_to_place_upscaled`](crate::place::win_to_place_upscaled).
+    pub fn win_to_place_upscaled(&mut self, win_probs: &[f64], k: usize, fit_rank_idx: usize) -> &[f64] {
+        self.populate(win_probs, fit_rank_idx + 1);
+        let probs = std::mem::take(&mut self.probs);
+        classic::summary_no_alloc(&probs, &mut self.classic_alloc(probs.cols(), probs.rows()));
+        self.probs = probs;
+        let runners = win_probs.len();
+        let rank_probs = &mut self.rank_probs[..runners];
+        let lambda = fit_lambda_no_alloc(win_probs, fit_rank_idx, self.summary.row_slice(fit_rank_idx), rank_probs);
+        let place_probs = &mut self.place_probs[..runners];
+        upscale_no_alloc(win_probs, k, |_| lambda, rank_probs, place_probs);
+        place_probs
+    }
+
+    /// Equivalent of [`win_to_place_upscaled_per_rank`](crate::place::win_to_place_upscaled_per_rank).
+    pub fn win_to_place_upscaled_per_rank(&mut self, win_probs: &[f64], k: usize, curve: &LambdaCurve) -> &[f64] {
+        let runners = win_probs.len();
+        self.reserve(runners, 0);
+        let place_probs = &mut self.place_probs[..runners];
+        upscale_no_alloc(
+            win_probs,
+            k,
+            |rank_idx| curve.lambda(rank_idx),
+            &mut self.rank_probs[..runners],
+            place_probs,
+        );
+        place_probs
+    }
+
+    fn populate(&mut self, win_probs: &[f64], k: usize) {
+        self.reserve(win_probs.len(), k);
+        DilatedProbs::default()
+            .with_win_probs(Capture::Borrowed(win_probs))
+            .with_dilatives(Capture::Borrowed(&self.dilatives[..k]))
+            .populate(&mut self.probs);
+    }
+
+    fn place_probs(&mut self, runners: usize, redistribute: bool) -> &[f64] {
+        let place_probs = &mut self.place_probs[..runners];
+        position::top_no_alloc(&self.summary, place_probs);
+        if redistribute {
+            place_probs.redistribute();
+        }
+        place_probs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::place::{
+        win_to_place_harville, win_to_place_mass_samp, win_to_place_sticky_samp, win_to_place_upscaled,
+        win_to_place_upscaled_per_rank,
+    };
+    use crate::testing::assert_slice_f64_relative;
+
+    const WIN_PROBS: [f64; 6] = [0.3, 0.25, 0.2, 0.12, 0.08, 0.05];
+
+    #[test]
+    fn matches_allocating_estimators() {
+        let mut workspace = Workspace::default();
+        for (win_probs, k) in [(&WIN_PROBS[..], 3), (&WIN_PROBS[..4], 2), (&WIN_PROBS[..], 4)] {
+            let mut win_probs = win_probs.to_vec();
+            win_probs.normalise(1.0);
+            assert_slice_f64_relative(
+                &win_to_place_harville(&win_probs, k),
+                workspace.win_to_place_harville(&win_probs, k),
+                1e-12,
+            );
+            assert_slice_f64_relative(
+                &win_to_place_mass_samp(&win_probs, k, 2),
+                workspace.win_to_place_mass_samp(&win_probs, k, 2),
+                1e-12,
+            );
+            assert_slice_f64_relative(
+                &win_to_place_sticky_samp(&win_probs, k, 2),
+                workspace.win_to_place_sticky_samp(&win_probs, k, 2),
+                1e-12,
+            );
+            assert_slice_f64_relative(
+                &win_to_place_upscaled(&win_probs, k, 1),
+                workspace.win_to_place_upscaled(&win_probs, k, 1),
+                1e-12,
+            );
+            let curve = LambdaCurve::fit(&win_probs, 2);
+            assert_slice_f64_relative(
+                &win_to_place_upscaled_per_rank(&win_probs, k, &curve),
+                workspace.win_to_place_upscaled_per_rank(&win_probs, k, &curve),
+                1e-12,
+            );
+        }
+        assert_eq!((6, 4), (workspace.runners(), workspace.ranks()));
+    }
+
+    #[test]
+    fn reuses_buffers() {
+        // the pointer and capacity of each vector, and the pointer of each matrix, bitmap and
+        // sticky permuter constituent
+        fn buffers(workspace: &Workspace) -> Vec<(*const u8, usize)> {
+            fn buffer<T>(vec: &[T], capacity: usize) -> (*const u8, usize) {
+                (vec.as_ptr().cast(), capacity)
+            }
+            let sticky_alloc = workspace.sticky_alloc.as_ref().unwrap();
+            vec![
+                buffer(&workspace.dilatives, workspace.dilatives.capacity()),
+                buffer(workspace.probs.flatten(), 0),
+                buffer(workspace.remaining.flatten(), 0),
+                buffer(workspace.summary.flatten(), 0),
+                buffer(&workspace.podium, workspace.podium.capacity()),
+                buffer(&workspace.tree, workspace.tree.capacity()),
+                buffer(&workspace.sorted_runners, workspace.sorted_runners.capacity()),
+                buffer(&workspace.sans_self_runners, workspace.sans_self_runners.capacity()),
+                buffer(&workspace.sans_self_podium, workspace.sans_self_podium.capacity()),
+                buffer(&workspace.rank_probs, workspace.rank_probs.capacity()),
+                buffer(&workspace.place_probs, workspace.place_probs.capacity()),
+                buffer(workspace.bitmap.as_words(), 0),
+                buffer(&sticky_alloc.combiner_ordinals, 0),
+                buffer(&sticky_alloc.whole_ordinals_stack, 0),
+                buffer(&sticky_alloc.omitted, 0),
+                buffer(&sticky_alloc.ordinals, 0),
+            ]
+        }
+
+        fn price_all(workspace: &mut Workspace, win_probs: &[f64], k: usize) {
+            let curve = LambdaCurve::fit(win_probs, 1);
+            workspace.win_to_place_harville(win_probs, k);
+            workspace.win_to_place_mass_samp(win_probs, k, 2);
+            workspace.win_to_place_sticky_samp(win_probs, k, 2);
+            workspace.win_to_place_rand_samp(win_probs, k, 2);
+            workspace.win_to_place_upscaled(win_probs, k, 1);
+            workspace.win_to_place_upscaled_per_rank(win_probs, k, &curve);
+        }
+
+        let mut workspace = Workspace::new(6, 4);
+        price_all(&mut workspace, &WIN_PROBS, 4);
+        let warmed_up = buffers(&workspace);
+
+        let mut smaller = WIN_PROBS[..4].to_vec();
+        smaller.normalise(1.0);
+        for (win_probs, k) in [(&smaller[..], 2), (&WIN_PROBS[..], 3), (&WIN_PROBS[..], 4)] {
+            price_all(&mut workspace, win_probs, k);
+            assert_eq!(warmed_up, buffers(&workspace));
+        }
+    }
+
+    #[test]
+    fn rand_samp_sums_to_places() {
+        let mut workspace = Workspace::new(6, 3);
+        let place_probs = workspace.win_to_place_rand_samp(&WIN_PROBS, 3, 4);
+        assert_eq!(6, place_probs.len());
+        assert_float_eq::assert_float_relative_eq!(3.0, place_probs.sum(), 1e-9);
+    }
+}
@@ -0,0 +1,213 @@
+//! Finishing-position markets derived from a rank matrix (summary), such as that produced by
+//! [`classic`](crate::harville::classic), [`mass_samp`](crate::harville::mass_samp) or
+//! [`sticky_samp`](crate::harville::sticky_samp).
+//!
+//! The rows of a summary are ranks and its columns are runners. Positions are zero-based, in
+//! keeping with the rank indices of the summary: position 0 is the winner.
+
+use crate::matrix::Matrix;
+use std::ops::RangeInclusive;
+
+/// Probability of each runner finishing within the first `summary.rows()` positions. These are
+/// the column sums of the summary.
+pub fn top(summary: &Matrix<f64>) -> Vec<f64> {
+    let mut top = vec![0.0; summary.cols()];
+    top_no_alloc(summary, &mut top);
+    top
+}
+
+/// Writes the probability of each runner finishing within the first `summary.rows()` positions
+/// into `top`, whose length must equal the number of runners.
+pub fn top_no_alloc(summary: &Matrix<f64>, top: &mut [f64]) {
+    debug_assert_eq!(
+        summary.cols(),
+        top.len(),
+        "number of columns in the summary matrix must equal to the target length"
+    );
+    top.fill(0.0);
+    for rank_probs in summary {
+        for (prob, rank_prob) in top.iter_mut().zip(rank_probs) {
+            *prob += rank_prob;
+        }
+    }
+}
+
+/// Probability of each runner finishing in one of the given `positions`.
+pub fn range(summary: &Matrix<f64>, positions: RangeInclusive<usize>) -> Vec<f64> {
+    assert!(
+        *positions.end() < summary.rows(),
+        "positions {positions:?} must be within the {} ranks of the summary",
+        summary.rows()
+    );
+    (0..summary.cols())
+        .map(|runner| positions.clone().map(|rank| summary[(rank, runner)]).sum())
+        .collect()
+}
+
+/// Probability of each runner finishing outside the first `k` positions.
+pub fn outside_top(summary: &Matrix<f64>, k: usize) -> Vec<f64> {
+    assert!(
+        k <= summary.rows(),
+        "k ({k}) cannot exceed the number of ranks in the summary ({})",
+        summary.rows()
+    );
+    if k == 0 {
+        return vec![1.0; summary.cols()];
+    }
+    range(summary, 0..=k - 1)
+        .into_iter()
+        .map(|prob| 1.0 - prob)
+        .collect()
+}
+
+/// Outside mass up to which a [`Distribution`] is still deemed to lie entirely within the
+/// summarised positions, absorbing the rounding residue of the summary.
+pub const OUTSIDE_TOLERANCE: f64 = 1e-9;
+
+/// The distribution of a runner's finishing position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution {
+    /// Probability of finishing in each summarised position, from 0 to the number of ranks in
+    /// the summary (exclusive).
+    pub probs: Vec<f64>,
+
+    /// Probability of finishing outside the summarised positions. This is zero where the summary
+    /// spans the entire field.
+    pub outside: f64,
+}
+
+impl Distribution {
+    /// The expected (zero-based) finishing position, or `None` if more than
+    /// [`OUTSIDE_TOLERANCE`] of the probability mass lies outside the summarised positions.
+    pub fn expectation(&self) -> Option<f64> {
+        if self.outside > OUTSIDE_TOLERANCE {
+            return None;
+        }
+        Some(
+            self.probs
+                .iter()
+                .enumerate()
+                .map(|(position, prob)| position as f64 * prob)
+                .sum(),
+        )
+    }
+
+    /// The variance of the finishing position, or `None` if more than [`OUTSIDE_TOLERANCE`] of
+    /// the probability mass lies outside the summarised positions.
+    pub fn variance(&self) -> Option<f64> {
+        let expectation = self.expectation()?;
+        Some(
+            self.probs
+                .iter()
+                .enumerate()
+                .map(|(position, prob)| (position as f64 - expectation).powi(2) * prob)
+                .sum(),
+        )
+    }
+}
+
+/// The finishing-position distribution of each runner.
+///
+/// Where the summary spans fewer ranks than there are runners, the mass of each runner finishing
+/// outside the summarised ranks is reported as a single [`outside`](Distribution::outside)
+/// bucket, since the summary does not tell how it is spread over the remaining positions.
+pub fn distributions(summary: &Matrix<f64>) -> Vec<Distribution> {
+    let ranks = summary.rows();
+    let runners = summary.cols();
+    assert!(
+        ranks <= runners,
+        "number of ranks ({ranks}) cannot exceed the number of runners ({runners})"
+    );
+    (0..runners)
+        .map(|runner| {
+            let probs = summary.col(runner).copied().collect::<Vec<_>>();
+            let outside = if runners > ranks {
+                (1.0 - probs.iter().sum::<f64>()).max(0.0)
+            } else {
+                0.0
+            };
+            Distribution { probs, outside }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harville::classic;
+    use crate::probs::SliceExt;
+    use crate::testing::{assert_slice_f64_relative, dilated_probs};
+    use assert_float_eq::*;
+
+    #[test]
+    fn top_and_outside() {
+        let summary = classic::summary(&dilated_probs(&[0.4, 0.3, 0.2, 0.1], 2));
+        let top = top(&summary);
+        assert_float_relative_eq!(2.0, top.sum());
+        let outside = outside_top(&summary, 2);
+        for (top, outside) in top.iter().zip(outside.iter()) {
+            assert_float_relative_eq!(1.0, top + outside);
+        }
+        assert_slice_f64_relative(&[0.6, 0.7, 0.8, 0.9], &outside_top(&summary, 1), 1e-12);
+        assert_eq!(vec![1.0; 4], outside_top(&summary, 0));
+    }
+
+    #[test]
+    fn range_of_positions() {
+        let summary = classic::summary(&dilated_probs(&[0.4, 0.3, 0.2, 0.1], 4));
+        let second_or_third = range(&summary, 1..=2);
+        for (runner, prob) in second_or_third.iter().enumerate() {
+            assert_float_relative_eq!(summary[(1, runner)] + summary[(2, runner)], *prob);
+        }
+        assert_slice_f64_relative(&[0.4, 0.3, 0.2, 0.1], &range(&summary, 0..=0), 1e-12);
+    }
+
+    #[test]
+    fn full_field_distributions() {
+        let summary = classic::summary(&dilated_probs(&[0.6, 0.3, 0.1], 3));
+        let distributions = distributions(&summary);
+        for distribution in &distributions {
+            assert_float_relative_eq!(1.0, distribution.probs.sum());
+        }
+        // expected positions over the field sum to 0 + 1 + 2
+        let expectations = distributions
+            .iter()
+            .map(|distribution| distribution.expectation().unwrap())
+            .collect::<Vec<_>>();
+        assert_float_relative_eq!(3.0, expectations.sum());
+        assert!(expectations[0] < expectations[1]);
+        assert!(distributions[0].variance().unwrap() > 0.0);
+        assert!(distributions.iter().all(|distribution| distribution.outside == 0.0));
+    }
+
+    #[test]
+    fn truncated_distributions() {
+        let summary = classic::summary(&dilated_probs(&[0.4, 0.3, 0.2, 0.1, 0.0], 2));
+        let distributions = distributions(&summary);
+        let distribution = &distributions[4];
+        assert_eq!(vec![0.0, 0.0], distribution.probs);
+        assert_float_relative_eq!(1.0, distribution.outside);
+        assert_eq!(None, distribution.expectation());
+        assert_eq!(None, distribution.variance());
+        for distribution in &distributions {
+            assert_float_relative_eq!(1.0, distribution.probs.sum() + distribution.outside);
+        }
+    }
+
+    #[test]
+    fn outside_residue() {
+        let distribution = Distribution { probs: vec![0.5, 0.5 - 1e-15], outside: 1e-15 };
+        assert_float_relative_eq!(0.5, distribution.expectation().unwrap(), 1e-12);
+        assert_float_relative_eq!(0.25, distribution.variance().unwrap(), 1e-12);
+
+        let distribution = Distribution { probs: vec![0.5, 0.49], outside: 0.01 };
+        assert_eq!(None, distribution.expectation());
+        assert_eq!(None, distribution.variance());
+    }
+
+    #[test]
+    #[should_panic(expected = "positions 1..=2 must be within the 2 ranks of the summary")]
+    fn range_out_of_bounds() {
+        range(&classic::summary(&dilated_probs(&[0.4, 0.3, 0.2, 0.1], 2)), 1..=2);
+    }
+}
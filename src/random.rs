@@ -7,6 +7,18 @@ pub fn uniform(rand: &mut impl Rand) -> f64 {
     rand.next_u64() as f64 / u64::MAX as f64
 }
 
+/// A uniformly distributed value in the open interval (0, 1), never returning either bound.
+#[inline]
+pub fn uniform_open(rand: &mut impl Rand) -> f64 {
+    ((rand.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+}
+
+/// A sample from the standard Gumbel distribution.
+#[inline]
+pub fn gumbel(rand: &mut impl Rand) -> f64 {
+    -(-uniform_open(rand).ln()).ln()
+}
+
 #[inline]
 pub fn gaussian_3_sigma(rand: &mut impl Rand) -> f64 {
     gaussian(rand, &Normal::new(0.5, 0.5 / 3.0).unwrap(), &(0.0..=1.0))
@@ -7,6 +7,7 @@ pub trait Generator {
     
     fn advance(&mut self) -> bool;
 
+    /// Bounds on the number of items yet to be read, counting the current item.
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
         (0, None)
@@ -40,7 +41,8 @@ impl<G: Generator> Itemiser for IntoItemiser<G> {
     fn next(&mut self) -> Option<&Self::Item> {
         let has_more = if self.initial {
             self.initial = false;
-            true
+            // a generator that knows itself to be empty has no current item
+            self.generator.size_hint().1 != Some(0)
         } else {
             self.generator.advance()
         };
@@ -53,6 +55,12 @@ impl<G: Generator> Itemiser for IntoItemiser<G> {
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.generator.size_hint()
+        let (lower, upper) = self.generator.size_hint();
+        if self.initial {
+            (lower, upper)
+        } else {
+            // the current item has already been yielded
+            (lower.saturating_sub(1), upper.map(|upper| upper.saturating_sub(1)))
+        }
     }
 }
\ No newline at end of file
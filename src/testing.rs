@@ -1,5 +1,26 @@
+use crate::capture::Capture;
+use crate::dilative::DilatedProbs;
+use crate::matrix::Matrix;
 use assert_float_eq::*;
 
+/// The undilated Harville probabilities of `places` podium places.
+pub fn dilated_probs(win_probs: &[f64], places: usize) -> Matrix<f64> {
+    Matrix::from(
+        DilatedProbs::default()
+            .with_win_probs(Capture::Borrowed(win_probs))
+            .with_podium_places(places),
+    )
+}
+
+/// The Harville probabilities of as many podium places as there are `dilatives`, dilated by them.
+pub fn dilated_probs_with(win_probs: &[f64], dilatives: &[f64]) -> Matrix<f64> {
+    Matrix::from(
+        DilatedProbs::default()
+            .with_win_probs(Capture::Borrowed(win_probs))
+            .with_dilatives(Capture::Borrowed(dilatives)),
+    )
+}
+
 pub fn assert_slice_f64_near(expected: &[f64], actual: &[f64], distance: u32) {
     assert_eq!(
         expected.len(),